@@ -0,0 +1,1554 @@
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use ffmpeg::ffi::{swr_build_matrix, swr_close, swr_init, swr_set_matrix, AVMatrixEncoding};
+use ffmpeg::{software, ChannelLayout};
+use parking_lot::Mutex;
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb, SharedRb};
+
+use crate::cache::Cache;
+use crate::error::{Error, Result};
+use crate::EventSenders;
+
+pub(crate) type AudioSampleProducer =
+    ringbuf::Producer<f32, Arc<SharedRb<f32, Vec<std::mem::MaybeUninit<f32>>>>>;
+pub(crate) type AudioSampleConsumer =
+    ringbuf::Consumer<f32, Arc<SharedRb<f32, Vec<std::mem::MaybeUninit<f32>>>>>;
+
+/// How many frames backends that don't report their buffer size pull at a time.
+const DEFAULT_BUFFER_FRAMES: usize = 1024;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// The sample formats audio devices can take. Byte order is always the native one; devices that want the
+/// other one are converted to by the backend's library.
+pub enum AudioSampleFormat {
+    /// Unsigned 8 bit.
+    U8,
+    /// Signed 8 bit.
+    I8,
+    /// Unsigned 16 bit.
+    U16,
+    /// Signed 16 bit.
+    I16,
+    /// Signed 32 bit.
+    I32,
+    /// 32 bit float.
+    F32,
+}
+
+/// Sample types mixed samples can be converted to.
+trait FromMixedSample {
+    fn from_mixed_sample(sample: f32) -> Self;
+}
+
+/// Convert a mixed sample (from `-1` to `1`) to a device's sample type. Out of range samples are clipped.
+fn convert_sample<T: FromMixedSample>(sample: f32) -> T {
+    T::from_mixed_sample(sample.clamp(-1., 1.))
+}
+
+impl FromMixedSample for f32 {
+    fn from_mixed_sample(sample: f32) -> Self {
+        sample
+    }
+}
+
+impl FromMixedSample for i32 {
+    fn from_mixed_sample(sample: f32) -> Self {
+        (sample as f64 * i32::MAX as f64) as i32
+    }
+}
+
+impl FromMixedSample for i16 {
+    fn from_mixed_sample(sample: f32) -> Self {
+        (sample * i16::MAX as f32) as i16
+    }
+}
+
+impl FromMixedSample for u16 {
+    fn from_mixed_sample(sample: f32) -> Self {
+        ((sample + 1.) * 0.5 * u16::MAX as f32) as u16
+    }
+}
+
+impl FromMixedSample for i8 {
+    fn from_mixed_sample(sample: f32) -> Self {
+        (sample * i8::MAX as f32) as i8
+    }
+}
+
+impl FromMixedSample for u8 {
+    fn from_mixed_sample(sample: f32) -> Self {
+        ((sample + 1.) * 0.5 * u8::MAX as f32) as u8
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// The format an [`AudioBackend`] outputs. Samples are always mixed as interleaved `f32`; converting them
+/// to the device's sample format is up to the backend.
+pub struct AudioSpec {
+    /// The sample rate, in Hz.
+    pub sample_rate: u32,
+    /// The number of interleaved channels.
+    pub channels: u16,
+    /// The device's sample format.
+    pub sample_format: AudioSampleFormat,
+    /// How many samples (across all channels) the device pulls at a time.
+    pub buffer_size: usize,
+}
+
+impl AudioSpec {
+    /// The layout of the channels, in the order they're interleaved in: ffmpeg's default layout for the
+    /// channel count, such as `FL FR FC LFE BL BR` for 5.1 and `FL FR FC LFE BL BR SL SR` for 7.1.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        ChannelLayout::default(self.channels as i32)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+/// The format to request from an audio device. Anything left as `None` is up to the device, and backends
+/// fall back to the closest format the device supports.
+pub struct DesiredAudioSpec {
+    /// The sample rate, in Hz, such as `44_100` or `48_000`.
+    pub sample_rate: Option<u32>,
+    /// The number of channels, such as `2` for stereo, `6` for 5.1 or `8` for 7.1.
+    pub channels: Option<u16>,
+    /// The sample format.
+    pub sample_format: Option<AudioSampleFormat>,
+}
+
+/// An audio output that [`crate::Player`]s can play through. Backends pull mixed samples from their
+/// [`AudioMixer`] whenever the device needs more.
+pub trait AudioBackend {
+    /// The format the backend outputs.
+    fn spec(&self) -> AudioSpec;
+    /// The mixer the backend pulls its samples from.
+    fn mixer(&self) -> &AudioMixer;
+    /// Start pulling samples, if the backend hasn't already.
+    fn resume(&self) -> Result<()>;
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// The levels surround audio is mixed down to fewer channels with. Gains are linear.
+pub enum DownmixMatrix {
+    /// ITU-R BS.775: center and surround channels at -3 dB.
+    Itu,
+    /// Center channel at +3 dB and surround channels at -6 dB, for clearer dialog.
+    DialogBoost,
+    /// Custom levels for the center and surround channels.
+    Custom {
+        /// The gain of the center channel.
+        center_level: f64,
+        /// The gain of the surround channels.
+        surround_level: f64,
+    },
+}
+
+impl DownmixMatrix {
+    fn levels(&self) -> (f64, f64) {
+        match self {
+            DownmixMatrix::Itu => (
+                std::f64::consts::FRAC_1_SQRT_2,
+                std::f64::consts::FRAC_1_SQRT_2,
+            ),
+            DownmixMatrix::DialogBoost => (std::f64::consts::SQRT_2, 0.5),
+            DownmixMatrix::Custom {
+                center_level,
+                surround_level,
+            } => (*center_level, *surround_level),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+/// How a [`crate::Player`]'s audio channels are mixed into the output's channels.
+pub struct ChannelMixing {
+    /// The levels used when the output has fewer channels than the audio.
+    pub downmix: DownmixMatrix,
+    /// The gain the LFE channel is mixed into the other channels with when the output has no LFE channel.
+    /// `0` (the ITU default) drops it.
+    pub lfe_level: f64,
+    /// Which channel of the output's layout (see [`AudioSpec::channel_layout`]) each device channel plays,
+    /// by index, for devices wired in a different order. Device channels mapped past the end of the
+    /// layout are silent. `None` plays the layout as is.
+    pub channel_map: Option<Vec<usize>>,
+    /// Audio channels (such as [`ChannelLayout::LOW_FREQUENCY`]) that are muted.
+    pub muted_channels: ChannelLayout,
+    /// Audio channels that are soloed. If any are, every other channel is muted.
+    pub soloed_channels: ChannelLayout,
+}
+
+impl Default for ChannelMixing {
+    fn default() -> Self {
+        Self {
+            downmix: DownmixMatrix::Itu,
+            lfe_level: 0.,
+            channel_map: None,
+            muted_channels: ChannelLayout::empty(),
+            soloed_channels: ChannelLayout::empty(),
+        }
+    }
+}
+
+impl ChannelMixing {
+    /// Whether a channel of the audio is heard, given the muted and soloed channels.
+    pub fn is_audible(&self, channel: ChannelLayout) -> bool {
+        let soloed = self.soloed_channels.is_empty() || self.soloed_channels.intersects(channel);
+        soloed && !self.muted_channels.intersects(channel)
+    }
+}
+
+/// The single channels of a layout, in the order they're interleaved in.
+pub fn layout_channels(channel_layout: ChannelLayout) -> Vec<ChannelLayout> {
+    (0..u64::BITS)
+        .map(|bit| ChannelLayout::from_bits_truncate(1 << bit))
+        .filter(|channel| !channel.is_empty() && channel_layout.contains(*channel))
+        .collect()
+}
+
+/// Rebuild the resampler's rematrixing (channel mixing) matrix. The resampler drops the few samples it
+/// was holding on to.
+pub(crate) fn apply_channel_mixing(
+    resampler: &mut software::resampling::Context,
+    channel_mixing: &ChannelMixing,
+) -> Result<()> {
+    let input_layout = resampler.input().channel_layout;
+    let output_layout = resampler.output().channel_layout;
+    let matrix = channel_matrix(input_layout, output_layout, channel_mixing)?;
+    let stride = input_layout.channels() as usize;
+
+    unsafe {
+        let swr_context = resampler.as_mut_ptr();
+        // the matrix can only be set on a closed context
+        swr_close(swr_context);
+        let matrix_status = swr_set_matrix(swr_context, matrix.as_ptr(), stride as c_int);
+        // reopened either way, so a rejected matrix falls back to the default one
+        let init_status = swr_init(swr_context);
+        let status = matrix_status.min(init_status);
+        if status < 0 {
+            return Err(Error::UnsupportedFormat(format!(
+                "failed to apply channel matrix: {}",
+                ffmpeg::Error::from(status)
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The rematrixing matrix mixing `input_layout` into `output_layout`. Rows are output channels, columns
+/// are input channels.
+fn channel_matrix(
+    input_layout: ChannelLayout,
+    output_layout: ChannelLayout,
+    channel_mixing: &ChannelMixing,
+) -> Result<Vec<f64>> {
+    if input_layout.is_empty() || output_layout.is_empty() {
+        return Err(Error::UnsupportedFormat(
+            "channel mixing needs known channel layouts".to_string(),
+        ));
+    }
+    let input_channels = layout_channels(input_layout);
+    let output_channel_count = output_layout.channels() as usize;
+    let stride = input_channels.len();
+    let (center_level, surround_level) = channel_mixing.downmix.levels();
+
+    let mut matrix = vec![0f64; output_channel_count * stride];
+    let status = unsafe {
+        swr_build_matrix(
+            input_layout.bits(),
+            output_layout.bits(),
+            center_level,
+            surround_level,
+            channel_mixing.lfe_level,
+            // what swresample uses for float output: no normalization, clipping happens later
+            i32::MAX as f64,
+            1.,
+            matrix.as_mut_ptr(),
+            stride as c_int,
+            AVMatrixEncoding::AV_MATRIX_ENCODING_NONE,
+            std::ptr::null_mut(),
+        )
+    };
+    if status < 0 {
+        return Err(Error::UnsupportedFormat(format!(
+            "failed to build channel matrix: {}",
+            ffmpeg::Error::from(status)
+        )));
+    }
+
+    for (column, channel) in input_channels.iter().enumerate() {
+        if !channel_mixing.is_audible(*channel) {
+            for row in matrix.chunks_exact_mut(stride) {
+                row[column] = 0.;
+            }
+        }
+    }
+
+    if let Some(channel_map) = channel_mixing.channel_map.as_ref() {
+        matrix = (0..output_channel_count)
+            .flat_map(|device_channel| match channel_map.get(device_channel) {
+                Some(&row) if row < output_channel_count => {
+                    matrix[row * stride..(row + 1) * stride].to_vec()
+                }
+                _ => vec![0.; stride],
+            })
+            .collect();
+    }
+    Ok(matrix)
+}
+
+/// How long fades on start, pause, stop and seek (and smoothing of gain changes) take, in ms.
+const FADE_MS: f32 = 10.;
+
+/// The level above which the master limiter starts compressing.
+const LIMITER_THRESHOLD: f32 = 0.8;
+
+/// How long it takes peak levels to fall by half, in ms.
+const PEAK_HALF_LIFE_MS: f32 = 200.;
+
+/// What [`StreamControls::discarded_samples`] holds while no samples are to be discarded.
+const NO_DISCARD: usize = usize::MAX;
+
+/// How many streams can be added or removed before the mixer's next pull picks them up.
+const MIXER_COMMAND_CAPACITY: usize = 64;
+
+/// How many streams a mixer has room for before adding one allocates during a pull.
+const MIXER_STREAM_CAPACITY: usize = 16;
+
+/// An `f32` that can be read and written from several threads without locking.
+struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+    fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+    fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed)
+    }
+    /// Raise the value to `value` if it's higher. Only meant for values that are never negative, whose bits
+    /// sort the same way they do.
+    fn fetch_max(&self, value: f32) {
+        self.0.fetch_max(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Per-channel levels, updated by the mixer and read from anywhere.
+fn new_levels(channels: u16) -> Box<[AtomicF32]> {
+    (0..channels).map(|_| AtomicF32::new(0.)).collect()
+}
+
+/// Let peak levels fall off by `decay`. The mixer is the only one writing them, so this doesn't race.
+fn decay_levels(peak_levels: &[AtomicF32], decay: f32) {
+    for peak_level in peak_levels {
+        peak_level.store(peak_level.load() * decay);
+    }
+}
+
+fn load_levels(peak_levels: &[AtomicF32]) -> Vec<f32> {
+    peak_levels.iter().map(AtomicF32::load).collect()
+}
+
+/// The settings of a stream that are changed through its [`AudioStreamHandle`]. They're atomics, so the
+/// mixer never waits on the handle.
+struct StreamControls {
+    gain: AtomicF32,
+    pan: AtomicF32,
+    muted: AtomicBool,
+    playing: AtomicBool,
+    /// Samples to fade out and drop, picked up by the mixer on its next pull. [`NO_DISCARD`] if there
+    /// are none.
+    discarded_samples: AtomicUsize,
+    peak_levels: Box<[AtomicF32]>,
+}
+
+struct MixerStream {
+    id: u64,
+    sample_consumer: AudioSampleConsumer,
+    audio_volume: Cache<f32>,
+    controls: Arc<StreamControls>,
+    /// The pan `channel_gains` were last computed for.
+    pan: f32,
+    /// The gain of each output channel for the pan.
+    channel_gains: Box<[f32]>,
+    /// The transport fade (start, pause, seek), from `0` to `1`.
+    fade: f32,
+    /// The gain actually applied, moving towards `audio_volume * gain` (or `0` when muted).
+    smoothed_gain: f32,
+    /// Samples left over from before a seek, faded out and skipped before playing the new ones.
+    stale_samples: usize,
+}
+
+impl MixerStream {
+    fn update_channel_gains(&mut self, channel_sides: &[f32]) {
+        let pan = self.controls.pan.load();
+        if pan == self.pan {
+            return;
+        }
+        self.pan = pan;
+        for (channel_gain, side) in self.channel_gains.iter_mut().zip(channel_sides) {
+            *channel_gain = (1. + side * pan).min(1.);
+        }
+    }
+
+    /// Add the stream's next samples to `output`.
+    fn mix_into(
+        &mut self,
+        output: &mut [f32],
+        channels: usize,
+        fade_step: f32,
+        peak_decay: f32,
+        channel_sides: &[f32],
+    ) {
+        let discarded_samples = self
+            .controls
+            .discarded_samples
+            .swap(NO_DISCARD, Ordering::Relaxed);
+        if discarded_samples != NO_DISCARD {
+            self.stale_samples = discarded_samples;
+        }
+        self.update_channel_gains(channel_sides);
+        decay_levels(&self.controls.peak_levels, peak_decay);
+        let target_gain = if self.controls.muted.load(Ordering::Relaxed) {
+            0.
+        } else {
+            self.audio_volume.get() * self.controls.gain.load()
+        };
+        let playing = self.controls.playing.load(Ordering::Relaxed);
+        for frame in output.chunks_exact_mut(channels) {
+            let target_fade = if playing && self.stale_samples == 0 {
+                1.
+            } else {
+                0.
+            };
+            if target_fade == 0. && self.fade <= 0. {
+                if self.stale_samples == 0 {
+                    // paused: keep the rest for when it resumes
+                    break;
+                }
+                // the rest of the stale samples are skipped silently, the new ones fade in
+                self.sample_consumer.skip(self.stale_samples);
+                self.stale_samples = 0;
+                continue;
+            }
+            self.fade = move_towards(self.fade, target_fade, fade_step);
+            self.smoothed_gain = move_towards(self.smoothed_gain, target_gain, fade_step);
+            let gain = self.fade * self.smoothed_gain;
+            for (channel, x) in frame.iter_mut().enumerate() {
+                let sample = self.sample_consumer.pop().unwrap_or(0.);
+                self.stale_samples = self.stale_samples.saturating_sub(1);
+                let sample = sample * gain * self.channel_gains[channel];
+                self.controls.peak_levels[channel].fetch_max(sample.abs());
+                *x += sample;
+            }
+        }
+    }
+}
+
+/// A change to the streams of a mix, picked up by the mixer on its next pull.
+enum MixerCommand {
+    Add(MixerStream),
+    Remove(u64),
+}
+
+/// The streams being mixed. Only the mixer locks it while pulling, so pulls never wait on players adding or
+/// removing streams.
+struct MixerState {
+    sample_streams: Vec<MixerStream>,
+    commands: HeapConsumer<MixerCommand>,
+    /// Removed streams are handed back to be dropped outside of the pull.
+    retired_streams: HeapProducer<MixerStream>,
+}
+
+impl MixerState {
+    fn apply_commands(&mut self) {
+        while let Some(command) = self.commands.pop() {
+            self.apply(command);
+        }
+    }
+
+    fn apply(&mut self, command: MixerCommand) {
+        match command {
+            MixerCommand::Add(sample_stream) => self.sample_streams.push(sample_stream),
+            MixerCommand::Remove(id) => {
+                if let Some(index) = self.sample_streams.iter().position(|s| s.id == id) {
+                    let sample_stream = self.sample_streams.swap_remove(index);
+                    // dropped right away if there's no room to hand it back
+                    let _ = self.retired_streams.push(sample_stream);
+                }
+            }
+        }
+    }
+}
+
+/// The players' side of a mix, which adds and removes streams by queueing commands for the mixer.
+struct MixerStreams {
+    state: Arc<Mutex<MixerState>>,
+    commands: HeapProducer<MixerCommand>,
+    retired_streams: HeapConsumer<MixerStream>,
+    next_stream_id: u64,
+    /// The events of the player each stream belongs to, which output errors are reported to.
+    stream_events: Vec<(u64, EventSenders)>,
+}
+
+impl MixerStreams {
+    fn send(&mut self, command: MixerCommand) {
+        self.retired_streams.clear();
+        if let Err(command) = self.commands.push(command) {
+            // nothing has pulled samples in a while (such as a manual capture backend), so the commands are
+            // applied without it
+            let mut state = self.state.lock();
+            state.apply_commands();
+            state.apply(command);
+            drop(state);
+            self.retired_streams.clear();
+        }
+    }
+}
+
+struct MixerControls {
+    master_gain: AtomicF32,
+    limiter_enabled: AtomicBool,
+    peak_levels: Box<[AtomicF32]>,
+}
+
+#[derive(Clone)]
+/// Mixes the sample streams of every [`crate::Player`] playing through an [`AudioBackend`]. Each stream
+/// has its own gain, pan and mute (see [`AudioStreamHandle`]), and the mix goes through a master gain
+/// and a soft limiter.
+pub struct AudioMixer {
+    state: Arc<Mutex<MixerState>>,
+    streams: Arc<Mutex<MixerStreams>>,
+    controls: Arc<MixerControls>,
+    sample_rate: u32,
+    channels: u16,
+    /// Which side of the output each channel is on: `-1` for left, `1` for right, `0` for neither.
+    channel_sides: Arc<[f32]>,
+}
+
+impl AudioMixer {
+    /// Create a mixer for an output with the given sample rate and number of channels.
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let left = ChannelLayout::FRONT_LEFT
+            | ChannelLayout::BACK_LEFT
+            | ChannelLayout::SIDE_LEFT
+            | ChannelLayout::FRONT_LEFT_OF_CENTER
+            | ChannelLayout::WIDE_LEFT;
+        let right = ChannelLayout::FRONT_RIGHT
+            | ChannelLayout::BACK_RIGHT
+            | ChannelLayout::SIDE_RIGHT
+            | ChannelLayout::FRONT_RIGHT_OF_CENTER
+            | ChannelLayout::WIDE_RIGHT;
+        let channel_sides = layout_channels(ChannelLayout::default(channels as i32))
+            .into_iter()
+            .map(|channel| {
+                if left.intersects(channel) {
+                    -1.
+                } else if right.intersects(channel) {
+                    1.
+                } else {
+                    0.
+                }
+            })
+            .chain(std::iter::repeat(0.))
+            .take(channels as usize)
+            .collect();
+        let (command_producer, command_consumer) =
+            HeapRb::<MixerCommand>::new(MIXER_COMMAND_CAPACITY).split();
+        let (retired_producer, retired_consumer) =
+            HeapRb::<MixerStream>::new(MIXER_COMMAND_CAPACITY).split();
+        let state = Arc::new(Mutex::new(MixerState {
+            sample_streams: Vec::with_capacity(MIXER_STREAM_CAPACITY),
+            commands: command_consumer,
+            retired_streams: retired_producer,
+        }));
+        Self {
+            streams: Arc::new(Mutex::new(MixerStreams {
+                state: Arc::clone(&state),
+                commands: command_producer,
+                retired_streams: retired_consumer,
+                next_stream_id: 0,
+                stream_events: vec![],
+            })),
+            state,
+            controls: Arc::new(MixerControls {
+                master_gain: AtomicF32::new(1.),
+                limiter_enabled: AtomicBool::new(true),
+                peak_levels: new_levels(channels),
+            }),
+            sample_rate,
+            channels,
+            channel_sides,
+        }
+    }
+
+    /// Add a stream to the mix, fading it in. It's removed once the returned handle drops. Errors of the
+    /// output are reported to `events`.
+    pub(crate) fn add_stream(
+        &self,
+        sample_consumer: AudioSampleConsumer,
+        audio_volume: Cache<f32>,
+        events: EventSenders,
+    ) -> AudioStreamHandle {
+        let controls = Arc::new(StreamControls {
+            gain: AtomicF32::new(1.),
+            pan: AtomicF32::new(0.),
+            muted: AtomicBool::new(false),
+            playing: AtomicBool::new(true),
+            discarded_samples: AtomicUsize::new(NO_DISCARD),
+            peak_levels: new_levels(self.channels),
+        });
+        let mut streams = self.streams.lock();
+        let id = streams.next_stream_id;
+        streams.next_stream_id += 1;
+        streams.stream_events.push((id, events));
+        streams.send(MixerCommand::Add(MixerStream {
+            id,
+            sample_consumer,
+            audio_volume,
+            controls: Arc::clone(&controls),
+            pan: 0.,
+            channel_gains: vec![1.; self.channels as usize].into(),
+            fade: 0.,
+            smoothed_gain: 0.,
+            stale_samples: 0,
+        }));
+        AudioStreamHandle {
+            id,
+            streams: Arc::clone(&self.streams),
+            controls,
+        }
+    }
+
+    /// Report an error of the output (such as the device being unplugged) to every player playing through it.
+    pub(crate) fn report_error(&self, e: Error) {
+        for (_, events) in self.streams.lock().stream_events.iter() {
+            events.emit_error(e.clone());
+        }
+    }
+
+    /// Set the gain of the whole mix.
+    pub fn set_master_gain(&self, gain: f32) {
+        self.controls.master_gain.store(gain.max(0.));
+    }
+
+    /// The gain of the whole mix.
+    pub fn master_gain(&self) -> f32 {
+        self.controls.master_gain.load()
+    }
+
+    /// Enable or disable the soft limiter on the mix (enabled by default). Without it, samples past full
+    /// scale are hard clipped.
+    pub fn set_limiter_enabled(&self, enabled: bool) {
+        self.controls
+            .limiter_enabled
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether the soft limiter is enabled.
+    pub fn limiter_enabled(&self) -> bool {
+        self.controls.limiter_enabled.load(Ordering::Relaxed)
+    }
+
+    /// The peak level of each channel of the mix, from `0` to `1`. Peaks fall off by half every
+    /// [`PEAK_HALF_LIFE_MS`], so reading them doesn't change them.
+    pub fn peak_levels(&self) -> Vec<f32> {
+        load_levels(&self.controls.peak_levels)
+    }
+
+    /// Fill `output` with the next interleaved samples of the mix. Streams that have run dry are silent.
+    /// This never blocks, so it's safe to call from a realtime audio callback: streams are added and removed
+    /// through a queue that's picked up here. It doesn't allocate either, unless more streams are added
+    /// than there was room for. Only meant to be called from one thread at a time; a concurrent call
+    /// outputs silence.
+    pub fn mix(&self, output: &mut [f32]) {
+        output.fill(0.);
+        let channels = self.channels.max(1) as usize;
+        let fade_step = 1000. / (FADE_MS * self.sample_rate.max(1) as f32);
+        let pulled_ms = (output.len() / channels) as f32 * 1000. / self.sample_rate.max(1) as f32;
+        let peak_decay = 0.5f32.powf(pulled_ms / PEAK_HALF_LIFE_MS);
+        if let Some(mut state) = self.state.try_lock() {
+            state.apply_commands();
+            for sample_stream in state.sample_streams.iter_mut() {
+                sample_stream.mix_into(
+                    output,
+                    channels,
+                    fade_step,
+                    peak_decay,
+                    &self.channel_sides,
+                );
+            }
+        }
+        decay_levels(&self.controls.peak_levels, peak_decay);
+
+        let master_gain = self.controls.master_gain.load();
+        let limiter_enabled = self.controls.limiter_enabled.load(Ordering::Relaxed);
+        for frame in output.chunks_exact_mut(channels) {
+            for (x, peak_level) in frame.iter_mut().zip(self.controls.peak_levels.iter()) {
+                *x *= master_gain;
+                *x = if limiter_enabled {
+                    soft_limit(*x)
+                } else {
+                    x.clamp(-1., 1.)
+                };
+                peak_level.fetch_max(x.abs());
+            }
+        }
+    }
+}
+
+/// Move `value` towards `target` by at most `step`.
+fn move_towards(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+/// Leave samples below [`LIMITER_THRESHOLD`] alone, and smoothly compress anything above it to stay
+/// within full scale.
+fn soft_limit(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= LIMITER_THRESHOLD {
+        return sample;
+    }
+    let headroom = 1. - LIMITER_THRESHOLD;
+    let limited_level =
+        LIMITER_THRESHOLD + headroom * ((level - LIMITER_THRESHOLD) / headroom).tanh();
+    limited_level.copysign(sample)
+}
+
+/// A stream playing through an [`AudioMixer`], such as the audio of a [`crate::Player`]. The stream is
+/// removed from the mix when this drops. Changing its settings never waits on the mixer.
+pub struct AudioStreamHandle {
+    id: u64,
+    streams: Arc<Mutex<MixerStreams>>,
+    controls: Arc<StreamControls>,
+}
+
+impl AudioStreamHandle {
+    /// Set the gain of the stream, on top of its volume. Changes are smoothed to avoid clicks.
+    pub fn set_gain(&self, gain: f32) {
+        self.controls.gain.store(gain.max(0.));
+    }
+
+    /// The gain of the stream.
+    pub fn gain(&self) -> f32 {
+        self.controls.gain.load()
+    }
+
+    /// Set the pan (balance) of the stream, from `-1` (left) to `1` (right). At `0` both sides play at
+    /// full level.
+    pub fn set_pan(&self, pan: f32) {
+        self.controls.pan.store(pan.clamp(-1., 1.));
+    }
+
+    /// The pan of the stream.
+    pub fn pan(&self) -> f32 {
+        self.controls.pan.load()
+    }
+
+    /// Mute or unmute the stream. Muted streams keep playing, silently.
+    pub fn set_muted(&self, muted: bool) {
+        self.controls.muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Whether the stream is muted.
+    pub fn is_muted(&self) -> bool {
+        self.controls.muted.load(Ordering::Relaxed)
+    }
+
+    /// The peak level of each channel of the stream, from `0` to `1`. Peaks fall off by half every
+    /// [`PEAK_HALF_LIFE_MS`], so reading them doesn't change them.
+    pub fn peak_levels(&self) -> Vec<f32> {
+        load_levels(&self.controls.peak_levels)
+    }
+
+    /// Fade the stream in and resume pulling its samples, or fade it out and hold on to the rest.
+    pub(crate) fn set_playing(&self, playing: bool) {
+        self.controls.playing.store(playing, Ordering::Relaxed);
+    }
+
+    /// Fade out and drop the `sample_count` samples currently buffered (such as after a seek), then fade
+    /// in whatever comes after them.
+    pub(crate) fn discard(&self, sample_count: usize) {
+        self.controls
+            .discarded_samples
+            .store(sample_count, Ordering::Relaxed);
+    }
+}
+
+impl Drop for AudioStreamHandle {
+    fn drop(&mut self) {
+        let mut streams = self.streams.lock();
+        streams.stream_events.retain(|(id, _)| *id != self.id);
+        streams.send(MixerCommand::Remove(self.id));
+    }
+}
+
+#[cfg(feature = "sdl2")]
+pub use self::sdl::SdlBackend;
+
+#[cfg(feature = "sdl2")]
+mod sdl {
+    use sdl2::audio::{AudioCallback, AudioFormat, AudioFormatNum, AudioSpecDesired};
+
+    use super::{
+        convert_sample, AudioBackend, AudioMixer, AudioSampleFormat, AudioSpec, DesiredAudioSpec,
+        FromMixedSample,
+    };
+    use crate::error::{Error, Result};
+
+    /// Plays audio through an SDL2 playback device. Needs to be kept alive for as long as it's used.
+    pub struct SdlBackend {
+        device: Box<dyn SdlDevice>,
+        mixer: AudioMixer,
+    }
+
+    /// An opened device, whatever its sample type.
+    trait SdlDevice {
+        fn spec(&self) -> &sdl2::audio::AudioSpec;
+        fn resume(&self);
+    }
+
+    impl<T: AudioFormatNum + FromMixedSample> SdlDevice for sdl2::audio::AudioDevice<SdlCallback<T>> {
+        fn spec(&self) -> &sdl2::audio::AudioSpec {
+            sdl2::audio::AudioDevice::spec(self)
+        }
+        fn resume(&self) {
+            sdl2::audio::AudioDevice::resume(self)
+        }
+    }
+
+    struct SdlCallback<T> {
+        mixer: AudioMixer,
+        mixed_samples: Vec<f32>,
+        _sample_type: std::marker::PhantomData<T>,
+    }
+
+    impl<T: AudioFormatNum + FromMixedSample> AudioCallback for SdlCallback<T> {
+        type Channel = T;
+        fn callback(&mut self, output: &mut [Self::Channel]) {
+            self.mixed_samples.resize(output.len(), 0.);
+            self.mixer.mix(&mut self.mixed_samples);
+            for (x, sample) in output.iter_mut().zip(&self.mixed_samples) {
+                *x = convert_sample(*sample);
+            }
+        }
+    }
+
+    impl SdlBackend {
+        /// Open the default playback device of an SDL2 audio subsystem, as 44.1 kHz stereo.
+        pub fn new(audio_sys: &sdl2::AudioSubsystem) -> Result<Self> {
+            Self::with_spec(
+                audio_sys,
+                DesiredAudioSpec {
+                    sample_rate: Some(44_100),
+                    channels: Some(2),
+                    sample_format: None,
+                },
+            )
+        }
+
+        /// Open the default playback device of an SDL2 audio subsystem in the desired format. SDL2 converts
+        /// to whatever the hardware actually takes, so the device always opens in the desired format.
+        pub fn with_spec(
+            audio_sys: &sdl2::AudioSubsystem,
+            desired_spec: DesiredAudioSpec,
+        ) -> Result<Self> {
+            let audio_spec = AudioSpecDesired {
+                freq: desired_spec.sample_rate.map(|rate| rate as i32),
+                channels: desired_spec.channels.map(|channels| channels as u8),
+                samples: None,
+            };
+            let (device, mixer) = match desired_spec.sample_format.unwrap_or(AudioSampleFormat::F32)
+            {
+                AudioSampleFormat::U8 => open::<u8>(audio_sys, &audio_spec)?,
+                AudioSampleFormat::I8 => open::<i8>(audio_sys, &audio_spec)?,
+                AudioSampleFormat::U16 => open::<u16>(audio_sys, &audio_spec)?,
+                AudioSampleFormat::I16 => open::<i16>(audio_sys, &audio_spec)?,
+                AudioSampleFormat::I32 => open::<i32>(audio_sys, &audio_spec)?,
+                AudioSampleFormat::F32 => open::<f32>(audio_sys, &audio_spec)?,
+            };
+            Ok(Self { device, mixer })
+        }
+    }
+
+    fn open<T: AudioFormatNum + FromMixedSample + 'static>(
+        audio_sys: &sdl2::AudioSubsystem,
+        audio_spec: &AudioSpecDesired,
+    ) -> Result<(Box<dyn SdlDevice>, AudioMixer)> {
+        let mut mixer = None;
+        let device = audio_sys
+            .open_playback(None, audio_spec, |spec| {
+                // the mixer needs the rate and channels the device actually opened with
+                let callback_mixer = AudioMixer::new(spec.freq as u32, spec.channels as u16);
+                mixer = Some(callback_mixer.clone());
+                SdlCallback::<T> {
+                    mixer: callback_mixer,
+                    mixed_samples: vec![],
+                    _sample_type: std::marker::PhantomData,
+                }
+            })
+            .map_err(Error::AudioDevice)?;
+        let mixer = mixer.ok_or_else(|| Error::AudioDevice("device did not open".to_string()))?;
+        Ok((Box::new(device), mixer))
+    }
+
+    impl From<AudioFormat> for AudioSampleFormat {
+        fn from(audio_format: AudioFormat) -> Self {
+            match audio_format {
+                AudioFormat::U8 => AudioSampleFormat::U8,
+                AudioFormat::S8 => AudioSampleFormat::I8,
+                AudioFormat::U16LSB | AudioFormat::U16MSB => AudioSampleFormat::U16,
+                AudioFormat::S16LSB | AudioFormat::S16MSB => AudioSampleFormat::I16,
+                AudioFormat::S32LSB | AudioFormat::S32MSB => AudioSampleFormat::I32,
+                AudioFormat::F32LSB | AudioFormat::F32MSB => AudioSampleFormat::F32,
+            }
+        }
+    }
+
+    impl AudioBackend for SdlBackend {
+        fn spec(&self) -> AudioSpec {
+            let spec = self.device.spec();
+            AudioSpec {
+                sample_rate: spec.freq as u32,
+                channels: spec.channels as u16,
+                sample_format: spec.format.into(),
+                buffer_size: spec.samples as usize * spec.channels as usize,
+            }
+        }
+        fn mixer(&self) -> &AudioMixer {
+            &self.mixer
+        }
+        fn resume(&self) -> Result<()> {
+            self.device.resume();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "cpal")]
+pub use self::cpal_backend::CpalBackend;
+
+#[cfg(feature = "cpal")]
+mod cpal_backend {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{BufferSize, SampleFormat, SizedSample, SupportedStreamConfig};
+
+    use super::{
+        convert_sample, AudioBackend, AudioMixer, AudioSampleFormat, AudioSpec, DesiredAudioSpec,
+        FromMixedSample, DEFAULT_BUFFER_FRAMES,
+    };
+    use crate::error::{Error, Result};
+
+    /// Plays audio through a cpal output stream. Needs to be kept alive for as long as it's used.
+    pub struct CpalBackend {
+        stream: cpal::Stream,
+        spec: AudioSpec,
+        mixer: AudioMixer,
+    }
+
+    impl CpalBackend {
+        /// Open the default output device of the default host, in its default configuration.
+        pub fn new() -> Result<Self> {
+            Self::with_spec(DesiredAudioSpec::default())
+        }
+
+        /// Open the default output device of the default host, in the configuration it supports that's
+        /// closest to the desired one. Channel count is matched first, then sample format, then sample rate.
+        pub fn with_spec(desired_spec: DesiredAudioSpec) -> Result<Self> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| Error::AudioDevice("no output device available".to_string()))?;
+            let supported_config = negotiate_config(&device, desired_spec)?;
+            let sample_format = match supported_config.sample_format() {
+                SampleFormat::U8 => AudioSampleFormat::U8,
+                SampleFormat::I8 => AudioSampleFormat::I8,
+                SampleFormat::U16 => AudioSampleFormat::U16,
+                SampleFormat::I16 => AudioSampleFormat::I16,
+                SampleFormat::I32 => AudioSampleFormat::I32,
+                SampleFormat::F32 => AudioSampleFormat::F32,
+                sample_format => {
+                    return Err(Error::UnsupportedFormat(format!(
+                        "audio device format {sample_format:?}"
+                    )))
+                }
+            };
+            let config = supported_config.config();
+            let mixer = AudioMixer::new(config.sample_rate.0, config.channels);
+            let stream = match sample_format {
+                AudioSampleFormat::U8 => build_stream::<u8>(&device, &config, mixer.clone()),
+                AudioSampleFormat::I8 => build_stream::<i8>(&device, &config, mixer.clone()),
+                AudioSampleFormat::U16 => build_stream::<u16>(&device, &config, mixer.clone()),
+                AudioSampleFormat::I16 => build_stream::<i16>(&device, &config, mixer.clone()),
+                AudioSampleFormat::I32 => build_stream::<i32>(&device, &config, mixer.clone()),
+                AudioSampleFormat::F32 => build_stream::<f32>(&device, &config, mixer.clone()),
+            }?;
+            // some hosts start playing as soon as the stream is built
+            stream
+                .pause()
+                .map_err(|e| Error::AudioDevice(e.to_string()))?;
+            let buffer_frames = match config.buffer_size {
+                BufferSize::Fixed(frames) => frames as usize,
+                BufferSize::Default => DEFAULT_BUFFER_FRAMES,
+            };
+            let spec = AudioSpec {
+                sample_rate: config.sample_rate.0,
+                channels: config.channels,
+                sample_format,
+                buffer_size: buffer_frames * config.channels as usize,
+            };
+            Ok(Self {
+                stream,
+                spec,
+                mixer,
+            })
+        }
+    }
+
+    /// The supported config closest to the desired spec, filling in what's left open from the device's
+    /// default config.
+    fn negotiate_config(
+        device: &cpal::Device,
+        desired_spec: DesiredAudioSpec,
+    ) -> Result<SupportedStreamConfig> {
+        let default_config = device
+            .default_output_config()
+            .map_err(|e| Error::AudioDevice(e.to_string()))?;
+        if desired_spec == DesiredAudioSpec::default() {
+            return Ok(default_config);
+        }
+        let channels = desired_spec.channels.unwrap_or(default_config.channels());
+        let sample_rate = desired_spec
+            .sample_rate
+            .unwrap_or(default_config.sample_rate().0);
+        let sample_format = desired_spec
+            .sample_format
+            .map(|sample_format| match sample_format {
+                AudioSampleFormat::U8 => SampleFormat::U8,
+                AudioSampleFormat::I8 => SampleFormat::I8,
+                AudioSampleFormat::U16 => SampleFormat::U16,
+                AudioSampleFormat::I16 => SampleFormat::I16,
+                AudioSampleFormat::I32 => SampleFormat::I32,
+                AudioSampleFormat::F32 => SampleFormat::F32,
+            })
+            .unwrap_or(default_config.sample_format());
+        let supported_configs = device
+            .supported_output_configs()
+            .map_err(|e| Error::AudioDevice(e.to_string()))?;
+        let closest_config = supported_configs
+            .map(|range| {
+                let rate = sample_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+                let mismatch = (
+                    range.channels().abs_diff(channels),
+                    range.sample_format() != sample_format,
+                    rate.abs_diff(sample_rate),
+                );
+                (mismatch, range.with_sample_rate(cpal::SampleRate(rate)))
+            })
+            .min_by_key(|(mismatch, _)| *mismatch)
+            .map(|(_, config)| config);
+        Ok(closest_config.unwrap_or(default_config))
+    }
+
+    fn build_stream<T: SizedSample + FromMixedSample>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mixer: AudioMixer,
+    ) -> Result<cpal::Stream> {
+        let mut mixed_samples = Vec::new();
+        let error_mixer = mixer.clone();
+        device
+            .build_output_stream(
+                config,
+                move |output: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    mixed_samples.resize(output.len(), 0.);
+                    mixer.mix(&mut mixed_samples);
+                    for (x, sample) in output.iter_mut().zip(&mixed_samples) {
+                        *x = convert_sample(*sample);
+                    }
+                },
+                // such as the device being unplugged
+                move |e| error_mixer.report_error(Error::AudioDevice(e.to_string())),
+                None,
+            )
+            .map_err(|e| Error::AudioDevice(e.to_string()))
+    }
+
+    impl AudioBackend for CpalBackend {
+        fn spec(&self) -> AudioSpec {
+            self.spec
+        }
+        fn mixer(&self) -> &AudioMixer {
+            &self.mixer
+        }
+        fn resume(&self) -> Result<()> {
+            self.stream
+                .play()
+                .map_err(|e| Error::AudioDevice(e.to_string()))
+        }
+    }
+}
+
+/// An output without a device, which records the mixed samples in memory instead of playing them. Useful
+/// for testing audio without a sound card (such as on CI), or as a silent output.
+pub struct CaptureBackend {
+    spec: AudioSpec,
+    mixer: AudioMixer,
+    captured_samples: Arc<Mutex<Vec<f32>>>,
+    realtime: bool,
+    alive: Arc<AtomicBool>,
+}
+
+impl CaptureBackend {
+    /// Create a [`CaptureBackend`] that, once resumed, pulls samples on a background thread at the pace a
+    /// real device would.
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            spec: AudioSpec {
+                sample_rate,
+                channels,
+                sample_format: AudioSampleFormat::F32,
+                buffer_size: DEFAULT_BUFFER_FRAMES * channels as usize,
+            },
+            mixer: AudioMixer::new(sample_rate, channels),
+            captured_samples: Arc::new(Mutex::new(Vec::new())),
+            realtime: true,
+            alive: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Create a [`CaptureBackend`] that only pulls samples when [`CaptureBackend::render`] is called.
+    pub fn manual(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            realtime: false,
+            ..Self::new(sample_rate, channels)
+        }
+    }
+
+    /// Pull `frames` frames from the mixer, as a device would, and record them. Returns the pulled samples.
+    pub fn render(&self, frames: usize) -> Vec<f32> {
+        render(
+            &self.mixer,
+            &self.captured_samples,
+            frames * self.spec.channels as usize,
+        )
+    }
+
+    /// All the samples recorded so far, interleaved.
+    pub fn captured_samples(&self) -> Vec<f32> {
+        self.captured_samples.lock().clone()
+    }
+
+    /// Take the samples recorded so far, clearing the recording.
+    pub fn take_captured_samples(&self) -> Vec<f32> {
+        std::mem::take(&mut *self.captured_samples.lock())
+    }
+}
+
+fn render(mixer: &AudioMixer, captured_samples: &Mutex<Vec<f32>>, sample_count: usize) -> Vec<f32> {
+    let mut mixed_samples = vec![0.; sample_count];
+    mixer.mix(&mut mixed_samples);
+    captured_samples.lock().extend_from_slice(&mixed_samples);
+    mixed_samples
+}
+
+impl AudioBackend for CaptureBackend {
+    fn spec(&self) -> AudioSpec {
+        self.spec
+    }
+    fn mixer(&self) -> &AudioMixer {
+        &self.mixer
+    }
+    fn resume(&self) -> Result<()> {
+        if !self.realtime || self.alive.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let mixer = self.mixer.clone();
+        let captured_samples = Arc::clone(&self.captured_samples);
+        let thread_alive = Arc::clone(&self.alive);
+        let spec = self.spec;
+        let pull_interval = std::time::Duration::from_secs_f64(
+            (spec.buffer_size / spec.channels.max(1) as usize) as f64
+                / spec.sample_rate.max(1) as f64,
+        );
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            let mut pulled_frames = 0;
+            while thread_alive.load(Ordering::Relaxed) {
+                std::thread::sleep(pull_interval);
+                // catch up with the wall clock instead of drifting by however long each pull took
+                let due_frames = (start.elapsed().as_secs_f64() * spec.sample_rate as f64) as usize;
+                let frames = due_frames - pulled_frames;
+                render(&mixer, &captured_samples, frames * spec.channels as usize);
+                pulled_frames = due_frames;
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Drop for CaptureBackend {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffmpeg::format::{sample, Sample};
+    use ffmpeg::frame::Audio;
+    use std::f64::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+    #[test]
+    fn mixed_samples_convert_to_every_device_format() {
+        assert_eq!(convert_sample::<f32>(0.25), 0.25);
+        assert_eq!(convert_sample::<i32>(1.), i32::MAX);
+        assert_eq!(convert_sample::<i32>(0.), 0);
+        assert_eq!(convert_sample::<i32>(-1.), -i32::MAX);
+        assert_eq!(convert_sample::<i16>(1.), i16::MAX);
+        assert_eq!(convert_sample::<i16>(0.), 0);
+        assert_eq!(convert_sample::<i16>(-1.), -i16::MAX);
+        assert_eq!(convert_sample::<i8>(1.), i8::MAX);
+        assert_eq!(convert_sample::<i8>(-1.), -i8::MAX);
+        // unsigned formats are centered on half their range
+        assert_eq!(convert_sample::<u16>(1.), u16::MAX);
+        assert_eq!(convert_sample::<u16>(0.), u16::MAX / 2);
+        assert_eq!(convert_sample::<u16>(-1.), 0);
+        assert_eq!(convert_sample::<u8>(1.), u8::MAX);
+        assert_eq!(convert_sample::<u8>(0.), u8::MAX / 2);
+        assert_eq!(convert_sample::<u8>(-1.), 0);
+    }
+
+    #[test]
+    fn out_of_range_samples_are_clipped() {
+        assert_eq!(convert_sample::<f32>(3.), 1.);
+        assert_eq!(convert_sample::<f32>(-3.), -1.);
+        assert_eq!(convert_sample::<i16>(1.5), i16::MAX);
+        assert_eq!(convert_sample::<u8>(-2.), 0);
+        assert_eq!(convert_sample::<u16>(2.), u16::MAX);
+    }
+
+    #[test]
+    fn channel_layouts_follow_the_channel_count() {
+        let layout = |channels| {
+            AudioSpec {
+                sample_rate: 48000,
+                channels,
+                sample_format: AudioSampleFormat::F32,
+                buffer_size: 0,
+            }
+            .channel_layout()
+        };
+        assert_eq!(layout(1), ChannelLayout::MONO);
+        assert_eq!(layout(2), ChannelLayout::STEREO);
+        assert_eq!(layout(6), ChannelLayout::_5POINT1_BACK);
+        assert_eq!(layout(8), ChannelLayout::_7POINT1);
+    }
+
+    // 5.1 is interleaved as front left, front right, center, LFE, side left, side right
+    const SURROUND: ChannelLayout = ChannelLayout::_5POINT1;
+
+    fn assert_matrix(matrix: &[f64], expected: &[f64]) {
+        assert_eq!(matrix.len(), expected.len(), "{matrix:?}");
+        for (level, expected_level) in matrix.iter().zip(expected) {
+            assert!(
+                (level - expected_level).abs() < 1e-6,
+                "{matrix:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn itu_downmix() {
+        let matrix = channel_matrix(SURROUND, ChannelLayout::STEREO, &ChannelMixing::default());
+        let c = FRAC_1_SQRT_2;
+        #[rustfmt::skip]
+        let expected = [
+            1., 0., c, 0., c, 0.,
+            0., 1., c, 0., 0., c,
+        ];
+        assert_matrix(&matrix.unwrap(), &expected);
+    }
+
+    #[test]
+    fn dialog_boost_downmix_with_lfe() {
+        let channel_mixing = ChannelMixing {
+            downmix: DownmixMatrix::DialogBoost,
+            lfe_level: 0.5,
+            ..Default::default()
+        };
+        let matrix = channel_matrix(SURROUND, ChannelLayout::STEREO, &channel_mixing);
+        // swresample splits the LFE between both front channels, at -3 dB each
+        let lfe = 0.5 * FRAC_1_SQRT_2;
+        #[rustfmt::skip]
+        let expected = [
+            1., 0., SQRT_2, lfe, 0.5, 0.,
+            0., 1., SQRT_2, lfe, 0., 0.5,
+        ];
+        assert_matrix(&matrix.unwrap(), &expected);
+    }
+
+    #[test]
+    fn solo_mutes_every_other_channel() {
+        let channel_mixing = ChannelMixing {
+            soloed_channels: ChannelLayout::FRONT_CENTER,
+            ..Default::default()
+        };
+        let matrix = channel_matrix(SURROUND, ChannelLayout::STEREO, &channel_mixing);
+        let c = FRAC_1_SQRT_2;
+        #[rustfmt::skip]
+        let expected = [
+            0., 0., c, 0., 0., 0.,
+            0., 0., c, 0., 0., 0.,
+        ];
+        assert_matrix(&matrix.unwrap(), &expected);
+    }
+
+    #[test]
+    fn mute_drops_channels() {
+        let channel_mixing = ChannelMixing {
+            muted_channels: ChannelLayout::SIDE_LEFT | ChannelLayout::SIDE_RIGHT,
+            ..Default::default()
+        };
+        let matrix = channel_matrix(SURROUND, ChannelLayout::STEREO, &channel_mixing);
+        let c = FRAC_1_SQRT_2;
+        #[rustfmt::skip]
+        let expected = [
+            1., 0., c, 0., 0., 0.,
+            0., 1., c, 0., 0., 0.,
+        ];
+        assert_matrix(&matrix.unwrap(), &expected);
+    }
+
+    #[test]
+    fn channel_map_reorders_device_channels() {
+        let swapped = ChannelMixing {
+            channel_map: Some(vec![1, 0]),
+            ..Default::default()
+        };
+        let matrix = channel_matrix(ChannelLayout::STEREO, ChannelLayout::STEREO, &swapped);
+        #[rustfmt::skip]
+        let expected = [
+            0., 1.,
+            1., 0.,
+        ];
+        assert_matrix(&matrix.unwrap(), &expected);
+
+        // device channels mapped past the end of the layout are silent
+        let out_of_range = ChannelMixing {
+            channel_map: Some(vec![0, 5]),
+            ..Default::default()
+        };
+        let matrix = channel_matrix(ChannelLayout::STEREO, ChannelLayout::STEREO, &out_of_range);
+        #[rustfmt::skip]
+        let expected = [
+            1., 0.,
+            0., 0.,
+        ];
+        assert_matrix(&matrix.unwrap(), &expected);
+    }
+
+    #[test]
+    fn unknown_layouts_are_rejected() {
+        let matrix = channel_matrix(
+            ChannelLayout::empty(),
+            ChannelLayout::STEREO,
+            &ChannelMixing::default(),
+        );
+        assert!(matches!(matrix, Err(Error::UnsupportedFormat(_))));
+    }
+
+    /// Resample a single 5.1 frame in which only `channel` has signal, returning the stereo output.
+    fn resample_one_channel(
+        resampler: &mut software::resampling::Context,
+        channel: usize,
+    ) -> Vec<f32> {
+        let frames = 64;
+        let mut input = Audio::new(Sample::F32(sample::Type::Packed), frames, SURROUND);
+        input.set_rate(48000);
+        for (i, bytes) in input.data_mut(0).chunks_exact_mut(4).enumerate() {
+            let sample: f32 = if i % 6 == channel { 1. } else { 0. };
+            bytes.copy_from_slice(&sample.to_ne_bytes());
+        }
+        let mut output = Audio::empty();
+        resampler.run(&input, &mut output).unwrap();
+        output.data(0)[..output.samples() * 2 * 4]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn mixing_is_applied_to_the_resampler() {
+        let format = Sample::F32(sample::Type::Packed);
+        let mut resampler = software::resampling::Context::get(
+            format,
+            SURROUND,
+            48000,
+            format,
+            ChannelLayout::STEREO,
+            48000,
+        )
+        .unwrap();
+        let channel_mixing = ChannelMixing {
+            soloed_channels: ChannelLayout::FRONT_CENTER,
+            channel_map: Some(vec![1, 0]),
+            ..Default::default()
+        };
+        // closes, rematrixes and reopens the resampler, which has to keep working afterwards
+        apply_channel_mixing(&mut resampler, &channel_mixing).unwrap();
+
+        let center = resample_one_channel(&mut resampler, 2);
+        assert!(!center.is_empty());
+        assert!(center
+            .iter()
+            .all(|sample| (*sample as f64 - FRAC_1_SQRT_2).abs() < 1e-4));
+        let front_left = resample_one_channel(&mut resampler, 0);
+        assert!(front_left.iter().all(|sample| *sample == 0.));
+
+        // mixing can be changed again on the same resampler
+        apply_channel_mixing(&mut resampler, &ChannelMixing::default()).unwrap();
+        let front_left = resample_one_channel(&mut resampler, 0);
+        let (left, right): (Vec<_>, Vec<_>) =
+            front_left.chunks_exact(2).map(|s| (s[0], s[1])).unzip();
+        assert!(left.iter().all(|sample| (*sample - 1.).abs() < 1e-4));
+        assert!(right.iter().all(|sample| *sample == 0.));
+    }
+
+    /// A sample rate at which fades (and gain changes) take exactly 8 frames, in steps of `0.125`.
+    const MIX_RATE: u32 = 800;
+
+    /// Add a stereo stream with `frames` frames at a constant `level` to the mix. The stream plays until
+    /// the returned handle drops.
+    fn add_constant_stream(
+        capture: &CaptureBackend,
+        level: f32,
+        frames: usize,
+    ) -> (AudioStreamHandle, AudioSampleProducer) {
+        let (mut producer, consumer) = SharedRb::<f32, Vec<_>>::new(frames * 2).split();
+        producer.push_slice(&vec![level; frames * 2]);
+        let handle = capture
+            .mixer()
+            .add_stream(consumer, Cache::new(1.), EventSenders::default());
+        (handle, producer)
+    }
+
+    fn assert_samples(samples: &[f32], expected: &[f32]) {
+        assert_eq!(samples.len(), expected.len(), "{samples:?}");
+        for (sample, expected_sample) in samples.iter().zip(expected) {
+            assert!(
+                (sample - expected_sample).abs() < 1e-4,
+                "{samples:?} != {expected:?}"
+            );
+        }
+    }
+
+    /// The left channel of interleaved stereo samples.
+    fn left(samples: &[f32]) -> Vec<f32> {
+        samples.iter().step_by(2).copied().collect()
+    }
+
+    #[test]
+    fn streams_fade_in_and_out() {
+        let capture = CaptureBackend::manual(MIX_RATE, 2);
+        let (handle, _producer) = add_constant_stream(&capture, 0.5, 100);
+        // the fade and the gain both move up from 0, by 0.125 a frame
+        let faded_in = left(&capture.render(10));
+        let expected = (1..=10)
+            .map(|frame| 0.5 * (frame as f32 * 0.125).min(1.).powi(2))
+            .collect::<Vec<_>>();
+        assert_samples(&faded_in, &expected);
+
+        // pausing fades out, then holds on to the rest
+        handle.set_playing(false);
+        let faded_out = left(&capture.render(10));
+        let expected = (1..=10)
+            .map(|frame| 0.5 * (1. - frame as f32 * 0.125).max(0.))
+            .collect::<Vec<_>>();
+        assert_samples(&faded_out, &expected);
+        let played_frames = 10 + 8;
+        handle.set_playing(true);
+        let resumed = capture.render(100);
+        let resumed_frames = resumed.iter().filter(|sample| **sample != 0.).count() / 2;
+        assert_eq!(resumed_frames, 100 - played_frames);
+    }
+
+    #[test]
+    fn gain_pan_and_mute() {
+        let capture = CaptureBackend::manual(MIX_RATE, 2);
+        let (handle, _producer) = add_constant_stream(&capture, 0.5, 100);
+        capture.render(10);
+
+        // gain changes are smoothed like fades
+        handle.set_gain(0.5);
+        let samples = capture.render(10);
+        assert_samples(&samples[18..], &[0.25, 0.25]);
+
+        // panned fully right, the left side is silent and the right side plays at full level
+        handle.set_pan(1.);
+        assert_samples(&capture.render(1), &[0., 0.25]);
+        handle.set_pan(-0.5);
+        assert_samples(&capture.render(1), &[0.25, 0.125]);
+
+        handle.set_muted(true);
+        let samples = capture.render(10);
+        assert_samples(&samples[18..], &[0., 0.]);
+        assert!(handle.is_muted());
+    }
+
+    #[test]
+    fn streams_are_summed_and_soft_limited() {
+        let capture = CaptureBackend::manual(MIX_RATE, 2);
+        let (_first, _first_producer) = add_constant_stream(&capture, 0.45, 100);
+        let (_second, _second_producer) = add_constant_stream(&capture, 0.45, 100);
+        let samples = capture.render(10);
+        let limited = LIMITER_THRESHOLD + (1. - LIMITER_THRESHOLD) * (0.1f32 / 0.2).tanh();
+        assert_samples(&samples[18..], &[limited, limited]);
+
+        // quiet samples aren't touched
+        capture.mixer().set_master_gain(0.5);
+        assert_samples(&capture.render(1), &[0.45, 0.45]);
+
+        // without the limiter, samples past full scale are clipped
+        capture.mixer().set_master_gain(2.);
+        capture.mixer().set_limiter_enabled(false);
+        assert_samples(&capture.render(1), &[1., 1.]);
+    }
+
+    #[test]
+    fn peak_levels_fall_off() {
+        let capture = CaptureBackend::manual(MIX_RATE, 2);
+        let (handle, _producer) = add_constant_stream(&capture, 0.5, 20);
+        handle.set_pan(1.);
+        capture.render(20);
+        assert_samples(&handle.peak_levels(), &[0., 0.5]);
+        assert_samples(&capture.mixer().peak_levels(), &[0., 0.5]);
+
+        // the stream has run dry, so a pull of one half life halves the peaks
+        capture.render(PEAK_HALF_LIFE_MS as usize * MIX_RATE as usize / 1000);
+        assert_samples(&handle.peak_levels(), &[0., 0.25]);
+        assert_samples(&capture.mixer().peak_levels(), &[0., 0.25]);
+    }
+
+    #[test]
+    fn removed_streams_leave_the_mix() {
+        let capture = CaptureBackend::manual(MIX_RATE, 2);
+        let (handle, _producer) = add_constant_stream(&capture, 0.5, 100);
+        capture.render(10);
+        drop(handle);
+        assert_samples(&capture.render(1), &[0., 0.]);
+        // more streams than the command queue holds are added and removed without a pull
+        for _ in 0..MIXER_COMMAND_CAPACITY * 2 {
+            add_constant_stream(&capture, 0.5, 1);
+        }
+        assert!(capture.render(1).iter().all(|sample| *sample == 0.));
+        assert!(capture.mixer().state.lock().sample_streams.is_empty());
+    }
+}
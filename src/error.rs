@@ -0,0 +1,88 @@
+use std::fmt;
+
+/// A [`std::result::Result`] with [`Error`] as the default error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(PartialEq, Clone, Debug)]
+/// The errors that can occur while opening or playing media.
+pub enum Error {
+    /// The media could not be opened.
+    Open(ffmpeg::Error),
+    /// The media could be opened, but its streams could not be found or read.
+    Probe(ffmpeg::Error),
+    /// A packet could not be decoded.
+    Decode(ffmpeg::Error),
+    /// The stream could not be seeked.
+    Seek(ffmpeg::Error),
+    /// A frame could not be encoded or saved.
+    Encode(ffmpeg::Error),
+    /// The audio device could not be opened or used.
+    AudioDevice(String),
+    /// The media uses a codec, pixel format or sample format that can't be played.
+    UnsupportedFormat(String),
+    /// Opening the media took longer than allowed.
+    Timeout,
+    /// A file could not be read or written.
+    Io(std::io::ErrorKind, String),
+    /// There is no track of the kind asked for with this stream index.
+    InvalidTrack(usize),
+    /// What was asked for isn't available for this media or player, such as seeking media of unknown
+    /// duration.
+    Unavailable(String),
+    /// The thread opening the media panicked.
+    LoaderPanicked,
+}
+
+impl Error {
+    /// Whether the error just means that the end of the stream has been reached.
+    pub fn is_eof(&self) -> bool {
+        matches!(self, Error::Decode(ffmpeg::Error::Eof))
+    }
+    /// Whether the error just means that the decoder needs another packet before it can output a frame.
+    pub(crate) fn needs_packet(&self) -> bool {
+        matches!(
+            self,
+            Error::Decode(ffmpeg::Error::Other {
+                errno: ffmpeg::error::EAGAIN
+            })
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Open(e) => write!(f, "failed to open media: {e}"),
+            Error::Probe(e) => write!(f, "failed to probe media: {e}"),
+            Error::Decode(e) => write!(f, "failed to decode: {e}"),
+            Error::Seek(e) => write!(f, "failed to seek: {e}"),
+            Error::Encode(e) => write!(f, "failed to encode: {e}"),
+            Error::AudioDevice(e) => write!(f, "audio device error: {e}"),
+            Error::UnsupportedFormat(e) => write!(f, "unsupported format: {e}"),
+            Error::Timeout => write!(f, "timed out while opening media"),
+            Error::Io(_, e) => write!(f, "io error: {e}"),
+            Error::InvalidTrack(stream_index) => write!(f, "no such track: stream {stream_index}"),
+            Error::Unavailable(e) => write!(f, "unavailable: {e}"),
+            Error::LoaderPanicked => write!(f, "the thread opening the media panicked"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Open(e)
+            | Error::Probe(e)
+            | Error::Decode(e)
+            | Error::Seek(e)
+            | Error::Encode(e) => Some(e),
+            Error::AudioDevice(_)
+            | Error::UnsupportedFormat(_)
+            | Error::Timeout
+            | Error::Io(..)
+            | Error::InvalidTrack(_)
+            | Error::Unavailable(_)
+            | Error::LoaderPanicked => None,
+        }
+    }
+}
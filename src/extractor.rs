@@ -0,0 +1,127 @@
+use chrono::Duration;
+use egui::ColorImage;
+use ffmpeg::media::Type;
+
+use crate::cache::Cache;
+use crate::error::{Error, Result};
+use crate::io::{MediaInput, MediaSource};
+use crate::{
+    timestamp_to_millisec, FrameQueue, PlayerState, SeekMode, Streamer, VideoStreamer,
+    AV_TIME_BASE_RATIONAL,
+};
+
+/// Decodes single frames from media without a [`crate::Player`] or a ui, such as for thumbnails.
+pub struct FrameExtractor {
+    video_streamer: VideoStreamer,
+    duration_ms: i64,
+}
+
+impl FrameExtractor {
+    /// Create a new [`FrameExtractor`] for the best video stream of a [`MediaSource`].
+    pub fn new(media_source: impl MediaSource) -> Result<Self> {
+        let input_context = media_source.open()?;
+        let video_stream_index = input_context
+            .streams()
+            .best(Type::Video)
+            .ok_or(Error::Probe(ffmpeg::Error::StreamNotFound))?
+            .index();
+        Self::from_input(input_context, video_stream_index)
+    }
+
+    /// Create a new [`FrameExtractor`] for the video stream `stream_index` of a [`MediaSource`].
+    pub(crate) fn with_stream(media_source: impl MediaSource, stream_index: usize) -> Result<Self> {
+        Self::from_input(media_source.open()?, stream_index)
+    }
+
+    fn from_input(input_context: MediaInput, video_stream_index: usize) -> Result<Self> {
+        let duration_ms =
+            timestamp_to_millisec(input_context.duration().max(0), AV_TIME_BASE_RATIONAL);
+        let video_streamer = VideoStreamer::new(
+            input_context,
+            video_stream_index,
+            Cache::new(PlayerState::Paused),
+            FrameQueue::default(),
+            Cache::new(0),
+            Cache::new(false),
+        )?;
+        Ok(Self {
+            video_streamer,
+            duration_ms,
+        })
+    }
+
+    /// The index of the video stream that frames are decoded from.
+    pub(crate) fn stream_index(&self) -> usize {
+        self.video_streamer.stream_index()
+    }
+
+    /// The duration of the media.
+    pub fn duration(&self) -> Duration {
+        Duration::milliseconds(self.duration_ms)
+    }
+
+    /// The native size of the video's frames, as `[width, height]`.
+    pub fn native_size(&self) -> [u32; 2] {
+        let (width, height) = self.video_streamer.native_size();
+        [width, height]
+    }
+
+    /// Decode the first frame at or after `time` (or the last frame, past the end), scaled to `size`
+    /// (`[width, height]`). If one of the dimensions is `0`, it's chosen to keep the video's aspect ratio.
+    pub fn frame_at(&mut self, time: Duration, size: [u32; 2]) -> Result<ColorImage> {
+        let [width, height] = self.output_size(size);
+        self.video_streamer.set_output_size(width, height)?;
+        let target_ms = match time.num_milliseconds().max(0) {
+            target_ms if self.duration_ms > 0 => target_ms.min(self.duration_ms),
+            target_ms => target_ms,
+        };
+        // past the last frame, an accurate seek runs into the end of the stream without finding a frame
+        for seek_mode in [SeekMode::Accurate, SeekMode::Keyframe] {
+            let mut image = None;
+            self.video_streamer
+                .seek_ms(target_ms, seek_mode, true, |frame| image = Some(frame))?;
+            if let Some(image) = image {
+                return Ok(image);
+            }
+        }
+        Err(Error::Decode(ffmpeg::Error::Eof))
+    }
+
+    /// Decode the frames shown at each of `times`. See [`FrameExtractor::frame_at`].
+    pub fn frames_at(&mut self, times: &[Duration], size: [u32; 2]) -> Result<Vec<ColorImage>> {
+        times
+            .iter()
+            .map(|time| self.frame_at(*time, size))
+            .collect()
+    }
+
+    /// Decode `count` frames, evenly spaced over the media (from the middle of each of `count` equal
+    /// parts). See [`FrameExtractor::frame_at`].
+    pub fn evenly_spaced_frames(
+        &mut self,
+        count: usize,
+        size: [u32; 2],
+    ) -> Result<Vec<ColorImage>> {
+        let times = (0..count)
+            .map(|i| {
+                let frac = (i as f64 + 0.5) / count as f64;
+                Duration::milliseconds((frac * self.duration_ms as f64) as i64)
+            })
+            .collect::<Vec<_>>();
+        self.frames_at(&times, size)
+    }
+
+    fn output_size(&self, [width, height]: [u32; 2]) -> [u32; 2] {
+        let [native_width, native_height] = self.native_size();
+        let aspect_ratio = native_width as f64 / native_height.max(1) as f64;
+        match (width, height) {
+            (0, 0) => [native_width, native_height],
+            (0, height) => [
+                ((height as f64 * aspect_ratio).round() as u32).max(1),
+                height,
+            ],
+            (width, 0) => [width, ((width as f64 / aspect_ratio).round() as u32).max(1)],
+            size => [size.0, size.1],
+        }
+    }
+}
@@ -0,0 +1,555 @@
+use std::ffi::{c_void, CStr, CString};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::{Deref, DerefMut};
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ffmpeg::ffi::{
+    av_free, av_freep, av_malloc, avformat_alloc_context, avformat_close_input,
+    avformat_find_stream_info, avformat_open_input, avio_alloc_context, avio_context_free,
+    AVIOContext, AVIOInterruptCB, AVERROR, AVERROR_EOF, AVFMT_FLAG_CUSTOM_IO, AVSEEK_FORCE,
+    AVSEEK_SIZE,
+};
+use ffmpeg::format::context::input::Input;
+use ffmpeg::Dictionary;
+use parking_lot::Mutex;
+
+use crate::error::{Error, Result};
+
+/// Size of the buffer ffmpeg reads into from a custom reader.
+const IO_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Anything that media can be read from.
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Where a [`crate::Player`] reads its media from. Every stream (video, audio) opens its own [`MediaInput`]
+/// from the source, so [`MediaSource::open`] may be called more than once.
+///
+/// Implemented for file paths ([`PathBuf`]), bytes (`Arc<[u8]>`), [`ReaderSource`], [`UrlSource`], and
+/// closures returning a [`MediaInput`] for custom demuxing.
+pub trait MediaSource: Send + Sync {
+    /// Open a new, independent input for the media.
+    fn open(&self) -> Result<MediaInput>;
+    /// Like [`MediaSource::open`], but any blocking io of the input fails with [`Error::Timeout`] once
+    /// `interrupt` is set. Sources that can't be interrupted just open normally.
+    fn open_interruptible(&self, _interrupt: &Arc<AtomicBool>) -> Result<MediaInput> {
+        self.open()
+    }
+}
+
+impl MediaSource for PathBuf {
+    fn open(&self) -> Result<MediaInput> {
+        open_path(self)
+    }
+    fn open_interruptible(&self, interrupt: &Arc<AtomicBool>) -> Result<MediaInput> {
+        let path = path_to_cstring(self)?;
+        open_input(Some(&path), None, Dictionary::new(), Some(interrupt))
+    }
+}
+
+impl MediaSource for Arc<[u8]> {
+    fn open(&self) -> Result<MediaInput> {
+        open_reader(std::io::Cursor::new(Arc::clone(self)))
+    }
+    fn open_interruptible(&self, interrupt: &Arc<AtomicBool>) -> Result<MediaInput> {
+        let custom_io = CustomIo::new(Box::new(std::io::Cursor::new(Arc::clone(self))))?;
+        open_input(None, Some(custom_io), Dictionary::new(), Some(interrupt))
+    }
+}
+
+impl<F> MediaSource for F
+where
+    F: Fn() -> Result<MediaInput> + Send + Sync,
+{
+    fn open(&self) -> Result<MediaInput> {
+        self()
+    }
+}
+
+/// A [`MediaSource`] reading from a single reader, which is shared between all inputs opened from it.
+pub struct ReaderSource {
+    reader: Arc<Mutex<Box<dyn ReadSeek>>>,
+}
+
+impl ReaderSource {
+    /// Create a new [`ReaderSource`].
+    pub fn new(reader: impl ReadSeek + 'static) -> Self {
+        let reader: Box<dyn ReadSeek> = Box::new(reader);
+        Self {
+            reader: Arc::new(Mutex::new(reader)),
+        }
+    }
+}
+
+impl ReaderSource {
+    fn shared_reader(&self) -> SharedReader {
+        SharedReader {
+            reader: Arc::clone(&self.reader),
+            position: 0,
+        }
+    }
+}
+
+impl MediaSource for ReaderSource {
+    fn open(&self) -> Result<MediaInput> {
+        open_reader(self.shared_reader())
+    }
+    fn open_interruptible(&self, interrupt: &Arc<AtomicBool>) -> Result<MediaInput> {
+        let custom_io = CustomIo::new(Box::new(self.shared_reader()))?;
+        open_input(None, Some(custom_io), Dictionary::new(), Some(interrupt))
+    }
+}
+
+/// A [`MediaSource`] for anything ffmpeg can open by url (`http://`, HLS `.m3u8` playlists, `rtsp://`,
+/// `file:`, ...).
+pub struct UrlSource {
+    url: String,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    headers: Vec<(String, String)>,
+    options: Vec<(String, String)>,
+}
+
+impl UrlSource {
+    /// Create a new [`UrlSource`].
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            timeout: None,
+            user_agent: None,
+            headers: Vec::new(),
+            options: Vec::new(),
+        }
+    }
+    /// Fail instead of waiting forever when connecting to or reading from the url stalls for this long.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    /// Set the user agent sent with http requests.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+    /// Add a header to send with http requests.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+    /// Set any other ffmpeg protocol or demuxer option, such as `reconnect` or `http_proxy`.
+    pub fn with_option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.push((key.into(), value.into()));
+        self
+    }
+    fn dictionary(&self) -> Dictionary {
+        let mut dictionary = Dictionary::new();
+        if let Some(timeout) = self.timeout {
+            // in microseconds. `timeout` covers connecting, `rw_timeout` covers reading once connected
+            let timeout_us = timeout.as_micros().to_string();
+            dictionary.set("timeout", &timeout_us);
+            dictionary.set("rw_timeout", &timeout_us);
+        }
+        if let Some(user_agent) = self.user_agent.as_ref() {
+            dictionary.set("user_agent", user_agent);
+        }
+        if !self.headers.is_empty() {
+            let headers: String = self
+                .headers
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}\r\n"))
+                .collect();
+            dictionary.set("headers", &headers);
+        }
+        for (key, value) in self.options.iter() {
+            dictionary.set(key, value);
+        }
+        dictionary
+    }
+}
+
+impl UrlSource {
+    fn open_url(&self, interrupt: Option<&Arc<AtomicBool>>) -> Result<MediaInput> {
+        ffmpeg::format::network::init();
+        let url = CString::new(self.url.as_str()).map_err(|_| {
+            Error::Open(ffmpeg::Error::Other {
+                errno: ffmpeg::error::EINVAL,
+            })
+        })?;
+        open_input(Some(&url), None, self.dictionary(), interrupt)
+    }
+}
+
+impl MediaSource for UrlSource {
+    fn open(&self) -> Result<MediaInput> {
+        self.open_url(None)
+    }
+    fn open_interruptible(&self, interrupt: &Arc<AtomicBool>) -> Result<MediaInput> {
+        self.open_url(Some(interrupt))
+    }
+}
+
+/// A [`MediaSource`] whose inputs are all opened with the same interrupt flag, such as by a
+/// [`crate::PlayerLoader`] that gives up on them when it times out.
+pub(crate) struct InterruptibleSource<S> {
+    pub(crate) source: S,
+    pub(crate) interrupt: Arc<AtomicBool>,
+}
+
+impl<S: MediaSource> MediaSource for InterruptibleSource<S> {
+    fn open(&self) -> Result<MediaInput> {
+        self.source.open_interruptible(&self.interrupt)
+    }
+}
+
+/// An opened [`Input`], along with the custom io context it reads through (if any).
+pub struct MediaInput {
+    // declared first so that it's closed before the io context it reads from gets freed
+    input: Input,
+    _custom_io: Option<CustomIo>,
+    // checked by the input's interrupt callback for as long as the input is open
+    _interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl Deref for MediaInput {
+    type Target = Input;
+    fn deref(&self) -> &Self::Target {
+        &self.input
+    }
+}
+
+impl DerefMut for MediaInput {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.input
+    }
+}
+
+/// For inputs opened some other way, such as by a [`MediaSource`] closure.
+impl From<Input> for MediaInput {
+    fn from(input: Input) -> Self {
+        Self {
+            input,
+            _custom_io: None,
+            _interrupt: None,
+        }
+    }
+}
+
+/// Open media from a path (or url) with ffmpeg's own io.
+pub fn open_path(path: impl AsRef<Path>) -> Result<MediaInput> {
+    let path = path_to_cstring(path.as_ref())?;
+    open_input(Some(&path), None, Dictionary::new(), None)
+}
+
+/// Open media from any reader, without going through the file system.
+pub fn open_reader(reader: impl ReadSeek + 'static) -> Result<MediaInput> {
+    let custom_io = CustomIo::new(Box::new(reader))?;
+    open_input(None, Some(custom_io), Dictionary::new(), None)
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString> {
+    path.to_str()
+        .and_then(|path| CString::new(path).ok())
+        .ok_or(Error::Open(ffmpeg::Error::Other {
+            errno: ffmpeg::error::EINVAL,
+        }))
+}
+
+/// Open an input by `url` with ffmpeg's own io, or through `custom_io`. If an `interrupt` flag is given,
+/// blocking io is given up on once it's set.
+fn open_input(
+    url: Option<&CStr>,
+    custom_io: Option<CustomIo>,
+    options: Dictionary,
+    interrupt: Option<&Arc<AtomicBool>>,
+) -> Result<MediaInput> {
+    unsafe {
+        let mut format_context = avformat_alloc_context();
+        if format_context.is_null() {
+            return Err(Error::Open(ffmpeg::Error::Other {
+                errno: ffmpeg::error::ENOMEM,
+            }));
+        }
+        if let Some(custom_io) = custom_io.as_ref() {
+            (*format_context).pb = custom_io.context;
+            (*format_context).flags |= AVFMT_FLAG_CUSTOM_IO as c_int;
+        }
+        if let Some(interrupt) = interrupt {
+            (*format_context).interrupt_callback = AVIOInterruptCB {
+                callback: Some(is_interrupted),
+                opaque: Arc::as_ptr(interrupt) as *mut c_void,
+            };
+        }
+        let mut options = options.disown();
+        // frees the format context on failure
+        let opened = avformat_open_input(
+            &mut format_context,
+            url.map_or(ptr::null(), CStr::as_ptr),
+            ptr::null(),
+            &mut options,
+        );
+        Dictionary::own(options);
+        if opened != 0 {
+            return Err(open_error(ffmpeg::Error::from(opened), Error::Open));
+        }
+        match avformat_find_stream_info(format_context, ptr::null_mut()) {
+            e if e < 0 => {
+                avformat_close_input(&mut format_context);
+                Err(open_error(ffmpeg::Error::from(e), Error::Probe))
+            }
+            _ => Ok(MediaInput {
+                input: Input::wrap(format_context),
+                _custom_io: custom_io,
+                _interrupt: interrupt.cloned(),
+            }),
+        }
+    }
+}
+
+/// Running out of time while opening (or being interrupted for it) is reported as [`Error::Timeout`].
+fn open_error(e: ffmpeg::Error, error: fn(ffmpeg::Error) -> Error) -> Error {
+    match e {
+        ffmpeg::Error::Exit => Error::Timeout,
+        ffmpeg::Error::Other { errno } if errno == ffmpeg::error::ETIMEDOUT => Error::Timeout,
+        e => error(e),
+    }
+}
+
+unsafe extern "C" fn is_interrupted(opaque: *mut c_void) -> c_int {
+    let interrupt = &*(opaque as *const AtomicBool);
+    interrupt.load(Ordering::Relaxed) as c_int
+}
+
+/// An `AVIOContext` that reads from a boxed [`ReadSeek`].
+struct CustomIo {
+    context: *mut AVIOContext,
+    reader: *mut Box<dyn ReadSeek>,
+}
+
+// the reader is `Send`, and the context is only ever used by the input that owns it
+unsafe impl Send for CustomIo {}
+
+impl CustomIo {
+    fn new(reader: Box<dyn ReadSeek>) -> Result<Self> {
+        let reader = Box::into_raw(Box::new(reader));
+        unsafe {
+            let buffer = av_malloc(IO_BUFFER_SIZE) as *mut u8;
+            let context = if buffer.is_null() {
+                ptr::null_mut()
+            } else {
+                avio_alloc_context(
+                    buffer,
+                    IO_BUFFER_SIZE as c_int,
+                    0,
+                    reader as *mut c_void,
+                    Some(read_packet),
+                    None,
+                    Some(seek),
+                )
+            };
+            if context.is_null() {
+                av_free(buffer as *mut c_void);
+                drop(Box::from_raw(reader));
+                return Err(Error::Open(ffmpeg::Error::Other {
+                    errno: ffmpeg::error::ENOMEM,
+                }));
+            }
+            Ok(Self { context, reader })
+        }
+    }
+}
+
+impl Drop for CustomIo {
+    fn drop(&mut self) {
+        unsafe {
+            // ffmpeg may have swapped out the buffer we gave it
+            av_freep(&mut (*self.context).buffer as *mut *mut u8 as *mut c_void);
+            avio_context_free(&mut self.context);
+            drop(Box::from_raw(self.reader));
+        }
+    }
+}
+
+unsafe extern "C" fn read_packet(
+    opaque: *mut c_void,
+    buffer: *mut u8,
+    buffer_size: c_int,
+) -> c_int {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let buffer = std::slice::from_raw_parts_mut(buffer, buffer_size.max(0) as usize);
+    match reader.read(buffer) {
+        Ok(0) => AVERROR_EOF,
+        Ok(read) => read as c_int,
+        Err(e) => AVERROR(e.raw_os_error().unwrap_or(ffmpeg::error::EIO)),
+    }
+}
+
+unsafe extern "C" fn seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let result = if whence & AVSEEK_SIZE as c_int != 0 {
+        stream_len(reader)
+    } else {
+        let seek_from = match whence & !(AVSEEK_FORCE as c_int) {
+            0 => SeekFrom::Start(offset.max(0) as u64),
+            1 => SeekFrom::Current(offset),
+            2 => SeekFrom::End(offset),
+            _ => return AVERROR(ffmpeg::error::EINVAL) as i64,
+        };
+        reader.seek(seek_from)
+    };
+    match result {
+        Ok(position) => position as i64,
+        Err(e) => AVERROR(e.raw_os_error().unwrap_or(ffmpeg::error::EIO)) as i64,
+    }
+}
+
+fn stream_len(reader: &mut impl Seek) -> std::io::Result<u64> {
+    let position = reader.stream_position()?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    Ok(len)
+}
+
+/// A reader shared between several inputs, each keeping track of its own position in it.
+struct SharedReader {
+    reader: Arc<Mutex<Box<dyn ReadSeek>>>,
+    position: u64,
+}
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut reader = self.reader.lock();
+        reader.seek(SeekFrom::Start(self.position))?;
+        let read = reader.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SharedReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let mut reader = self.reader.lock();
+        let start = match pos {
+            SeekFrom::Current(offset) => {
+                SeekFrom::Start(self.position.saturating_add_signed(offset))
+            }
+            pos => pos,
+        };
+        self.position = reader.seek(start)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ffmpeg::media::Type;
+
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/sine_440hz_1s.wav");
+
+    /// The data of every packet of the input, in order.
+    fn packet_data(input: &mut MediaInput) -> Vec<Vec<u8>> {
+        input
+            .packets()
+            .map(|(_, packet)| packet.data().unwrap_or_default().to_vec())
+            .collect()
+    }
+
+    fn assert_opens_the_fixture(source: &dyn MediaSource) {
+        let mut input = source.open().unwrap();
+        assert!(input.streams().best(Type::Audio).is_some());
+        assert_eq!(input.duration(), 1_000_000);
+        assert!(!packet_data(&mut input).is_empty());
+    }
+
+    #[test]
+    fn sources_open_the_media() {
+        let path = PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/sine_440hz_1s.wav"
+        ));
+        let bytes: Arc<[u8]> = FIXTURE.into();
+        let closure_path = path.clone();
+        assert_opens_the_fixture(&path);
+        assert_opens_the_fixture(&bytes);
+        assert_opens_the_fixture(&ReaderSource::new(Cursor::new(FIXTURE)));
+        assert_opens_the_fixture(&move || open_path(&closure_path));
+    }
+
+    #[test]
+    fn inputs_of_a_reader_source_read_independently() {
+        let source = ReaderSource::new(Cursor::new(FIXTURE));
+        let mut first = source.open().unwrap();
+        let mut second = source.open().unwrap();
+        // reading one input to the end doesn't move the other along
+        let first_packets = packet_data(&mut first);
+        assert_eq!(packet_data(&mut second), first_packets);
+    }
+
+    #[test]
+    fn interrupted_sources_time_out() {
+        let bytes: Arc<[u8]> = FIXTURE.into();
+        let interrupt = Arc::new(AtomicBool::new(true));
+        assert!(matches!(
+            bytes.open_interruptible(&interrupt),
+            Err(Error::Timeout)
+        ));
+    }
+
+    /// Call the custom io callbacks the way ffmpeg does, on a reader over `0..100`.
+    struct Callbacks {
+        reader: Box<Box<dyn ReadSeek>>,
+    }
+
+    impl Callbacks {
+        fn new() -> Self {
+            Self {
+                reader: Box::new(Box::new(Cursor::new((0..100).collect::<Vec<u8>>()))),
+            }
+        }
+        fn opaque(&mut self) -> *mut c_void {
+            &mut *self.reader as *mut Box<dyn ReadSeek> as *mut c_void
+        }
+        fn read(&mut self, buffer_size: usize) -> (c_int, Vec<u8>) {
+            let mut buffer = vec![0; buffer_size];
+            let read =
+                unsafe { read_packet(self.opaque(), buffer.as_mut_ptr(), buffer_size as c_int) };
+            buffer.truncate(read.max(0) as usize);
+            (read, buffer)
+        }
+        fn seek(&mut self, offset: i64, whence: c_int) -> i64 {
+            unsafe { seek(self.opaque(), offset, whence) }
+        }
+    }
+
+    #[test]
+    fn read_packet_reads_until_the_end() {
+        let mut callbacks = Callbacks::new();
+        assert_eq!(callbacks.read(4), (4, vec![0, 1, 2, 3]));
+        callbacks.seek(98, 0);
+        assert_eq!(callbacks.read(4), (2, vec![98, 99]));
+        assert_eq!(callbacks.read(4).0, AVERROR_EOF);
+    }
+
+    #[test]
+    fn seek_follows_whence() {
+        let mut callbacks = Callbacks::new();
+        assert_eq!(callbacks.seek(10, 0), 10);
+        assert_eq!(callbacks.seek(5, 1), 15);
+        assert_eq!(callbacks.seek(-20, 2), 80);
+        // the size is asked for without moving
+        assert_eq!(callbacks.seek(0, AVSEEK_SIZE as c_int), 100);
+        assert_eq!(callbacks.read(1), (1, vec![80]));
+        assert_eq!(callbacks.seek(3, AVSEEK_FORCE as c_int), 3);
+        assert_eq!(callbacks.seek(0, 7), AVERROR(ffmpeg::error::EINVAL) as i64);
+        // before the start is an error from the reader
+        assert!(callbacks.seek(-1000, 1) < 0);
+    }
+}
@@ -2,40 +2,125 @@
 //! egui-video
 //! video playback library for [`egui`]
 //!
+/// module for audio output backends
+pub mod audio;
 /// module for the caching arc mutex code
 pub mod cache;
+/// module for the error type
+pub mod error;
+/// module for decoding single frames without a player
+pub mod extractor;
+/// module for media sources (files, bytes, readers, urls)
+pub mod io;
+/// module for what's shown in place of the video of audio-only media
+mod placeholder;
+/// module for inspecting media without playing it
+pub mod probe;
+/// module for saving frames as images
+pub mod snapshot;
+/// module for subtitle decoding and rendering
+pub mod subtitle;
+/// module for seekbar preview thumbnails
+mod thumbnail;
 
 extern crate ffmpeg_next as ffmpeg;
 
-#[cfg(feature = "from_bytes")]
-use std::io::Write;
-
-use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
 use egui::epaint::Shadow;
 use egui::{
-    vec2, Align2, Color32, ColorImage, FontId, Image, Rect, Response, Rounding, Sense,
-    TextureHandle, TextureOptions, Ui,
+    pos2, vec2, Align2, Color32, ColorImage, FontId, Image, Rect, Response, Rounding, Sense,
+    Spinner, TextureHandle, TextureOptions, Ui,
 };
-use ffmpeg::ffi::AV_TIME_BASE;
+use ffmpeg::ffi::{AV_NOPTS_VALUE, AV_TIME_BASE};
 use ffmpeg::format::context::input::Input;
-use ffmpeg::format::{input, Pixel};
+use ffmpeg::format::Pixel;
 use ffmpeg::frame::Audio;
 use ffmpeg::media::Type;
 use ffmpeg::util::frame::video::Video;
-use ffmpeg::{rescale, Rational, Rescale};
+use ffmpeg::{rescale, Packet, Rational, Rescale};
 use ffmpeg::{software, ChannelLayout};
 use parking_lot::Mutex;
 use ringbuf::SharedRb;
-use sdl2::audio::{self, AudioCallback, AudioFormat, AudioSpecDesired};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::sync::Arc;
-use std::time::UNIX_EPOCH;
-use timer::{Guard, Timer};
+use std::thread::JoinHandle;
+use std::time::{Instant, UNIX_EPOCH};
 
+use crate::audio::{AudioSampleProducer, AudioStreamHandle};
+pub use crate::audio::{
+    AudioBackend, AudioSampleFormat, AudioSpec, ChannelMixing, DesiredAudioSpec, DownmixMatrix,
+};
 use crate::cache::Cache;
-use std::path::{Path, PathBuf};
-#[cfg(feature = "from_bytes")]
-use tempfile::NamedTempFile;
+pub use crate::error::Error;
+use crate::error::Result;
+pub use crate::extractor::FrameExtractor;
+pub use crate::io::MediaSource;
+use crate::io::{InterruptibleSource, MediaInput, ReadSeek, ReaderSource};
+use crate::placeholder::Visualizer;
+pub use crate::probe::MediaInfo;
+pub use crate::snapshot::ImageFormat;
+use crate::subtitle::{SubtitleStreamer, SubtitleTextures};
+use crate::thumbnail::ThumbnailCache;
+use std::path::Path;
+
+/// A scaler converting the decoder's frames to RGB24 of the given size.
+fn rgb_scaler(
+    video_decoder: &ffmpeg::decoder::Video,
+    width: u32,
+    height: u32,
+) -> Result<software::scaling::Context> {
+    software::scaling::Context::get(
+        video_decoder.format(),
+        video_decoder.width(),
+        video_decoder.height(),
+        Pixel::RGB24,
+        width,
+        height,
+        software::scaling::flag::Flags::BILINEAR,
+    )
+    .map_err(|e| Error::UnsupportedFormat(format!("failed to create scaler: {e}")))
+}
+
+/// A resampler converting the decoder's frames to the interleaved `f32` samples audio backends mix, with
+/// the given channel layout and sample rate.
+fn audio_resampler(
+    audio_decoder: &ffmpeg::decoder::Audio,
+    channel_layout: ChannelLayout,
+    sample_rate: u32,
+) -> Result<software::resampling::Context> {
+    software::resampling::context::Context::get(
+        audio_decoder.format(),
+        audio_decoder.channel_layout(),
+        audio_decoder.rate(),
+        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed),
+        channel_layout,
+        sample_rate,
+    )
+    .map_err(|e| Error::UnsupportedFormat(format!("failed to create resampler: {e}")))
+}
+
+/// Open a decoder for a video stream of the input, along with the stream's time base and start time.
+fn open_video_decoder(
+    input_context: &Input,
+    stream_index: usize,
+) -> Result<(ffmpeg::decoder::Video, Rational, i64)> {
+    let stream = input_context
+        .stream(stream_index)
+        .ok_or(Error::Probe(ffmpeg::Error::StreamNotFound))?;
+    let video_context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+        .map_err(Error::Probe)?;
+    let video_decoder = video_context
+        .decoder()
+        .video()
+        .map_err(|e| Error::UnsupportedFormat(format!("failed to open video decoder: {e}")))?;
+    Ok((
+        video_decoder,
+        stream.time_base(),
+        stream_start_time(&stream),
+    ))
+}
 
 fn format_duration(dur: Duration) -> String {
     let dt = DateTime::<Utc>::from(UNIX_EPOCH) + dur;
@@ -46,13 +131,63 @@ fn format_duration(dur: Duration) -> String {
     }
 }
 
-/// The playback device. Needs to be initialized (and kept alive!) for use by a [`Player`].
-pub type AudioDevice = audio::AudioDevice<AudioDeviceCallback>;
+#[cfg(feature = "sdl2")]
+/// The SDL2 playback device. Needs to be initialized (and kept alive!) for use by a [`Player`].
+pub type AudioDevice = audio::SdlBackend;
+
+type FrameQueue = Arc<Mutex<VecDeque<QueuedFrame>>>;
+
+/// Requests for the decode thread, which has the video streamer to itself while decoding. A request stays
+/// at the front of the queue until it has been handled.
+type VideoRequests = Arc<Mutex<VecDeque<VideoRequest>>>;
+
+/// Requests for the audio thread, which has the audio streamer to itself while decoding. A request stays
+/// at the front of the queue until it has been handled.
+type AudioRequests = Arc<Mutex<VecDeque<AudioRequest>>>;
+
+/// The playback speeds offered by the speed menu of the [`Player`] controls.
+const PLAYBACK_SPEEDS: [f32; 9] = [0.25, 0.5, 0.75, 1., 1.25, 1.5, 2., 3., 4.];
+/// The range of playback speeds supported by [`Player::set_playback_speed`].
+const PLAYBACK_SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.25..=4.;
+
+/// How far back to look for an earlier keyframe when stepping backwards from a keyframe.
+const STEP_BACKWARD_SEEK_MS: i64 = 1000;
+
+/// How wide the seekbar preview thumbnails are, in pixels.
+const SEEKBAR_PREVIEW_WIDTH: u32 = 160;
 
-type AudioSampleProducer =
-    ringbuf::Producer<f32, Arc<SharedRb<f32, Vec<std::mem::MaybeUninit<f32>>>>>;
-type AudioSampleConsumer =
-    ringbuf::Consumer<f32, Arc<SharedRb<f32, Vec<std::mem::MaybeUninit<f32>>>>>;
+/// How much the subtitle offset changes per click in the track menu.
+const SUBTITLE_OFFSET_STEP_MS: i64 = 100;
+
+/// How long a [`PlayerLoader`] waits for media to open, unless changed with [`PlayerLoader::with_timeout`].
+const DEFAULT_LOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How many of the audio backend's pulls worth of samples each audio stream buffers ahead.
+const AUDIO_SAMPLE_BUFFER_PULLS: usize = 4;
+/// How much audio each audio stream buffers ahead at least, in milliseconds, so that a whole decoded
+/// frame (after resampling) fits in the buffer even with small device buffers.
+const AUDIO_SAMPLE_BUFFER_MIN_MS: usize = 250;
+/// How long the audio thread waits before trying again when the sample buffer is full or there's nothing
+/// to decode.
+const AUDIO_THREAD_IDLE_WAIT: std::time::Duration = std::time::Duration::from_millis(2);
+
+/// How often audio-only media is redrawn (and stepped through), in frames per second.
+const AUDIO_ONLY_FRAME_RATE: f64 = 30.;
+
+/// The size of the visualizer shown for audio-only media without cover art.
+const AUDIO_ONLY_VISUALIZER_SIZE: [u32; 2] = [640, 360];
+
+/// How long the decode thread waits before checking again when it has nothing to do.
+const DECODE_THREAD_IDLE_WAIT: std::time::Duration = std::time::Duration::from_millis(5);
+
+/// How long the decode and audio threads back off for after an error, doubled each time the same error
+/// comes again, up to [`MAX_ERROR_BACKOFF`].
+const MIN_ERROR_BACKOFF: std::time::Duration = std::time::Duration::from_millis(10);
+const MAX_ERROR_BACKOFF: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How many undecodable packets may come before the first frame (e.g. in a stream that starts mid-GOP)
+/// before opening fails.
+const FIRST_FRAME_ATTEMPTS: usize = 256;
 
 /// Config struct behavior of the [`Player`]
 pub struct PlayerConfig {
@@ -60,6 +195,41 @@ pub struct PlayerConfig {
     pub looping: bool,
     /// whether to render video player controls or not
     pub show_controls: bool,
+    /// how many decoded frames the decode thread is allowed to buffer ahead of presentation
+    pub frame_queue_capacity: usize,
+    /// which clock the audio and video streams are synchronized to. needs to be set before [`Player::with_audio`]
+    pub sync_mode: SyncMode,
+    /// how far apart (in milliseconds) the audio and video streams can drift before being resynchronized.
+    /// needs to be set before [`Player::with_audio`]
+    pub sync_tolerance_ms: i64,
+    /// how precisely to seek. can be changed later with [`Player::set_seek_mode`]
+    pub seek_mode: SeekMode,
+    /// whether to show a preview thumbnail of the hovered location above the seekbar
+    pub show_seekbar_previews: bool,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// How a [`Player`] seeks.
+pub enum SeekMode {
+    /// Decode from the keyframe before the seek location up to the first frame at or after it.
+    Accurate,
+    /// Snap to the keyframe nearest to the seek location. Less precise, but fast enough for scrubbing.
+    Keyframe,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// The clock that the streams of a [`Player`] are synchronized to.
+pub enum SyncMode {
+    /// The audio stream is the master clock. Video frames are dropped or repeated to follow the audio
+    /// that is currently being heard. Falls back to the system clock if there is no audio stream.
+    AudioMaster,
+    /// The video stream is the master clock, and is presented by the system clock. Audio frames are
+    /// dropped or delayed to follow the video frame that is currently shown.
+    VideoMaster,
+    /// A clock supplied with [`Player::set_external_clock`] (such as a timeline, or another player) is the
+    /// master clock. The video is presented by it, and audio frames are dropped or delayed to follow it.
+    /// Works like [`SyncMode::VideoMaster`] until a clock is set.
+    External,
 }
 
 impl Default for PlayerConfig {
@@ -67,6 +237,11 @@ impl Default for PlayerConfig {
         PlayerConfig {
             looping: true,
             show_controls: true,
+            frame_queue_capacity: 8,
+            sync_mode: SyncMode::AudioMaster,
+            sync_tolerance_ms: 50,
+            seek_mode: SeekMode::Accurate,
+            show_seekbar_previews: true,
         }
     }
 }
@@ -74,14 +249,17 @@ impl Default for PlayerConfig {
 /// The [`Player`] processes and controls streams of video/audio. This is what you use to show a video file.
 /// Initialize once, and use the [`Player::ui`] or [`Player::ui_at()`] functions to show the playback.
 pub struct Player {
-    /// The video streamer of the player.
-    pub video_streamer: Arc<Mutex<VideoStreamer>>,
+    /// The video streamer of the player. Doesn't exist for audio-only media.
+    pub video_streamer: Option<Arc<Mutex<VideoStreamer>>>,
     /// The audio streamer of the player. Won't exist unless [`Player::with_audio`] is called and there exists
     /// a valid audio stream in the file.
     pub audio_streamer: Option<Arc<Mutex<AudioStreamer>>>,
+    /// The audio streamer's handle in its mixer, shared so that its settings and levels don't need the
+    /// audio streamer's lock.
+    audio_stream_handle: Option<Arc<AudioStreamHandle>>,
     /// The state of the player.
     pub player_state: Cache<PlayerState>,
-    /// The framerate of the video stream.
+    /// The framerate of the video stream (or of the visualizer, for audio-only media).
     pub framerate: f64,
     texture_options: TextureOptions,
     /// The player's texture handle.
@@ -90,10 +268,23 @@ pub struct Player {
     pub height: u32,
     /// The width of the video stream.
     pub width: u32,
-    frame_timer: Timer,
-    audio_timer: Timer,
-    audio_thread: Option<Guard>,
-    frame_thread: Option<Guard>,
+    audio_thread: Option<DecodeThread>,
+    decode_thread: Option<DecodeThread>,
+    frame_queue: FrameQueue,
+    video_requests: VideoRequests,
+    audio_requests: AudioRequests,
+    /// The video stream being played, as of the last [`VideoRequest::SelectStream`].
+    video_track: Option<usize>,
+    /// The audio stream being played, as of the last [`AudioRequest::SelectStream`].
+    audio_track: Option<usize>,
+    /// Steps forward that are waiting for the decode thread to queue their frame.
+    pending_steps: usize,
+    clock: PlaybackClock,
+    external_clock: Option<Box<dyn Fn() -> Duration + Send>>,
+    events: EventSenders,
+    reported_player_state: PlayerState,
+    first_frame_pending: bool,
+    buffer_underrun: bool,
     ctx_ref: egui::Context,
     /// The configuration for playback / rendering
     pub config: PlayerConfig,
@@ -101,14 +292,73 @@ pub struct Player {
     pub audio_volume: Cache<f32>,
     /// The maximum volume of the audio stream.
     pub max_audio_volume: f32,
+    playback_speed: Cache<f32>,
+    seek_mode: Cache<SeekMode>,
     duration_ms: i64,
     last_seek_ms: Option<i64>,
     preseek_player_state: Option<PlayerState>,
-    #[cfg(feature = "from_bytes")]
-    temp_file: Option<NamedTempFile>,
     video_elapsed_ms: Cache<i64>,
-    audio_elapsed_ms: Cache<i64>,
-    input_path: PathBuf,
+    audio_elapsed_ms: Cache<Option<i64>>,
+    /// How far the demuxers have read the video and audio streams, in milliseconds.
+    video_read_until_ms: Cache<i64>,
+    audio_read_until_ms: Cache<i64>,
+    /// Whether the video and audio streams have been decoded to their end since they were last seeked.
+    video_ended: Cache<bool>,
+    audio_ended: Cache<bool>,
+    media_source: Arc<dyn MediaSource>,
+    media_info: MediaInfo,
+    video_tracks: Vec<TrackInfo>,
+    /// The size of the frames of each video track, in the same order.
+    video_track_sizes: Vec<[u32; 2]>,
+    audio_tracks: Vec<TrackInfo>,
+    subtitle_tracks: Vec<TrackInfo>,
+    subtitle_streamer: Option<SubtitleStreamer>,
+    subtitle_textures: SubtitleTextures,
+    subtitle_offset_ms: i64,
+    subtitles_visible: bool,
+    thumbnails: Option<ThumbnailCache>,
+    /// Shown in place of the video for audio-only media.
+    cover_art: Option<ColorImage>,
+    /// The timestamp of the frame on screen, which [`Player::current_frame`] decodes again.
+    shown_frame_ms: Option<i64>,
+    /// Kept open between calls to [`Player::current_frame`].
+    snapshot_extractor: Option<FrameExtractor>,
+    channel_mixing: ChannelMixing,
+    visualizer: Option<Visualizer>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+/// A video, audio or subtitle stream in the media, which can be selected for playback.
+pub struct TrackInfo {
+    /// The index of the stream in the media.
+    pub stream_index: usize,
+    /// The name of the stream's codec.
+    pub codec: String,
+    /// The stream's language, if tagged.
+    pub language: Option<String>,
+    /// The stream's title, if tagged.
+    pub title: Option<String>,
+}
+
+impl TrackInfo {
+    fn from_stream(stream: &ffmpeg::Stream) -> Self {
+        let metadata = stream.metadata();
+        Self {
+            stream_index: stream.index(),
+            codec: stream.parameters().id().name().to_string(),
+            language: metadata.get("language").map(String::from),
+            title: metadata.get("title").map(String::from),
+        }
+    }
+    /// A short description of the track, for showing in menus.
+    pub fn label(&self) -> String {
+        let name = self
+            .title
+            .clone()
+            .or_else(|| self.language.clone())
+            .unwrap_or_else(|| format!("track {}", self.stream_index));
+        format!("{name} ({})", self.codec)
+    }
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -124,6 +374,158 @@ pub enum PlayerState {
     Paused,
     /// Playback is ongoing.
     Playing,
+    /// Playback is waiting for the frame queue to refill after it ran dry, such as when a network stream
+    /// can't be downloaded fast enough.
+    Buffering,
+}
+
+/// Opens a [`Player`] on a background thread, so that the ui doesn't freeze while the media is probed and
+/// its first frame is decoded. Created with [`Player::open_async`].
+pub struct PlayerLoader {
+    player_receiver: Receiver<Result<Player>>,
+    /// Set to make the loading thread give up on opening the media.
+    interrupt: Arc<AtomicBool>,
+    started_at: Instant,
+    timeout: std::time::Duration,
+    error: Option<Error>,
+    finished: bool,
+}
+
+impl PlayerLoader {
+    /// Set how long to wait for the media to open before failing with [`Error::Timeout`].
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Whether the media is still being opened.
+    pub fn is_loading(&self) -> bool {
+        !self.finished
+    }
+    /// The error that opening the media failed with, if it failed.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+    /// Check whether the media has finished opening. Returns the [`Player`] (or the error that opening it
+    /// failed with) exactly once, and [`None`] otherwise.
+    pub fn poll(&mut self) -> Option<Result<Player>> {
+        if self.finished {
+            return None;
+        }
+        let result = match self.player_receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) if self.started_at.elapsed() < self.timeout => return None,
+            Err(TryRecvError::Empty) => Err(Error::Timeout),
+            Err(TryRecvError::Disconnected) => Err(Error::LoaderPanicked),
+        };
+        self.finished = true;
+        if let Err(e) = result.as_ref() {
+            if *e == Error::Timeout {
+                self.interrupt.store(true, Ordering::Relaxed);
+            }
+            self.error = Some(e.clone());
+        }
+        Some(result)
+    }
+    /// Draw a placeholder for the player, showing that it is loading or why it failed to load.
+    pub fn ui(&mut self, ui: &mut Ui, size: [f32; 2]) -> Response {
+        let (rect, response) = ui.allocate_exact_size(size.into(), Sense::hover());
+        self.render_placeholder(ui, rect);
+        response
+    }
+    /// Draw a placeholder for the player with a specific rect.
+    pub fn ui_at(&mut self, ui: &mut Ui, rect: Rect) -> Response {
+        let response = ui.allocate_rect(rect, Sense::hover());
+        self.render_placeholder(ui, rect);
+        response
+    }
+    fn render_placeholder(&mut self, ui: &mut Ui, rect: Rect) {
+        ui.painter()
+            .rect_filled(rect, Rounding::none(), Color32::from_black_alpha(200));
+        if let Some(e) = self.error.as_ref() {
+            ui.painter().text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                e.to_string(),
+                FontId {
+                    size: 14.,
+                    ..Default::default()
+                },
+                Color32::WHITE,
+            );
+        } else {
+            paint_spinner(ui, rect);
+            // make sure the timeout gets noticed
+            ui.ctx()
+                .request_repaint_after(self.timeout.saturating_sub(self.started_at.elapsed()));
+        }
+    }
+}
+
+impl Drop for PlayerLoader {
+    fn drop(&mut self) {
+        // nobody is waiting for the player anymore
+        if !self.finished {
+            self.interrupt.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Draw a spinner in the center of `rect`, without affecting the layout of `ui`.
+fn paint_spinner(ui: &mut Ui, rect: Rect) {
+    let spinner_size = 24.;
+    let spinner_rect = Rect::from_center_size(rect.center(), vec2(spinner_size, spinner_size));
+    ui.child_ui(spinner_rect, *ui.layout())
+        .add(Spinner::new().size(spinner_size).color(Color32::WHITE));
+}
+
+#[derive(PartialEq, Clone, Debug)]
+/// Events emitted by a [`Player`]. See [`Player::events`].
+pub enum PlayerEvent {
+    /// The state of the player changed. Changes in seek location while seeking are not reported.
+    StateChanged(PlayerState),
+    /// A seek finished. Value is the seek location, in milliseconds.
+    Seeked(i64),
+    /// Playback reached the end of the file.
+    EndOfFile,
+    /// Playback reached the end of the file and started over from the beginning.
+    Looped,
+    /// A frame could not be decoded.
+    DecodeError(Error),
+    /// Any other error that occured during playback, such as a failed seek.
+    Error(Error),
+    /// Playback is waiting for the decode thread to catch up.
+    BufferUnderrun,
+    /// The first frame since the player was created or started has been shown.
+    FirstFrameReady,
+}
+
+/// Sends [`PlayerEvent`]s to every receiver handed out by [`Player::events`].
+#[derive(Clone, Default)]
+struct EventSenders {
+    senders: Arc<Mutex<Vec<Sender<PlayerEvent>>>>,
+    last_error: Arc<Mutex<Option<Error>>>,
+}
+
+impl EventSenders {
+    fn subscribe(&self) -> Receiver<PlayerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.lock().push(sender);
+        receiver
+    }
+    fn emit(&self, event: PlayerEvent) {
+        // receivers that have been dropped are forgotten
+        self.senders
+            .lock()
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+    fn emit_error(&self, e: Error) {
+        *self.last_error.lock() = Some(e.clone());
+        if matches!(e, Error::Decode(_)) {
+            self.emit(PlayerEvent::DecodeError(e))
+        } else {
+            self.emit(PlayerEvent::Error(e))
+        }
+    }
 }
 
 /// Streams video.
@@ -131,24 +533,291 @@ pub struct VideoStreamer {
     video_decoder: ffmpeg::decoder::Video,
     video_stream_index: usize,
     player_state: Cache<PlayerState>,
-    input_context: Input,
+    input_context: MediaInput,
     video_elapsed_ms: Cache<i64>,
-    _audio_elapsed_ms: Cache<i64>,
+    read_until_ms: Cache<i64>,
+    ended: Cache<bool>,
     scaler: software::scaling::Context,
+    frame_queue: FrameQueue,
+    time_base: Rational,
+    start_time: i64,
+    /// Where the last [`VideoRequest`] or seek left the stream: the timestamp of the frame it queued to be
+    /// presented immediately, or `0` if it went back to the beginning.
+    requested_position_ms: i64,
+}
+
+/// A decoded frame waiting in the [`FrameQueue`] to be presented.
+struct QueuedFrame {
+    image: ColorImage,
+    timestamp_ms: i64,
+    /// Frames such as seek previews are presented immediately, and move the [`PlaybackClock`] to their timestamp.
+    present_immediately: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Work the ui thread hands to the decode thread, instead of waiting for the video streamer itself.
+enum VideoRequest {
+    /// Go back to the beginning of the stream, dropping the queued frames.
+    Reset,
+    /// Seek, replacing the queued frames with a preview of the seek location.
+    Seek { seek_frac: f32, seek_mode: SeekMode },
+    /// Replace the queued frames with the frame before the one at `current_ms`, and move the audio to it.
+    /// Without `current_ms`, steps back from where the last request left the stream, which the ui may not
+    /// have shown yet.
+    StepBackward { current_ms: Option<i64> },
+    /// Decode another video stream, seeking it to `seek_frac` (unless the player is stopped).
+    SelectStream {
+        stream_index: usize,
+        seek_frac: Option<f32>,
+        seek_mode: SeekMode,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Work the ui (or the decode thread) hands to the audio thread, instead of waiting for the audio streamer
+/// itself.
+enum AudioRequest {
+    /// Go back to the beginning of the stream.
+    Reset,
+    /// Seek, dropping the queued samples.
+    Seek { seek_frac: f32, seek_mode: SeekMode },
+    /// Seek accurately to a video frame that was shown without playing up to it.
+    SeekToMs { target_ms: i64 },
+    /// Decode another audio stream, seeking it to `seek_ms` (unless the player is stopped).
+    SelectStream {
+        stream_index: usize,
+        seek_ms: Option<i64>,
+    },
+}
+
+/// Keeps the decode thread (or the audio thread) of a [`Player`] running. The thread exits once this is
+/// dropped, which waits for it to finish what it was doing.
+struct DecodeThread {
+    alive: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for DecodeThread {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[derive(Default)]
+/// Reports the errors of the decode thread (or the audio thread). An error that keeps coming, such as one
+/// from a source that has gone away, is only reported once, and the thread backs off while it does.
+struct ThreadErrors {
+    last_error: Option<Error>,
+    backoff: std::time::Duration,
+}
+
+impl ThreadErrors {
+    /// Report `e` unless it's the error reported last, returning how long the thread should back off for.
+    fn report(&mut self, events: &EventSenders, e: Error) -> std::time::Duration {
+        if self.last_error.as_ref() == Some(&e) {
+            self.backoff = (self.backoff * 2).min(MAX_ERROR_BACKOFF);
+        } else {
+            events.emit_error(e.clone());
+            self.last_error = Some(e);
+            self.backoff = MIN_ERROR_BACKOFF;
+        }
+        self.backoff
+    }
+    /// The thread got something done, so the next error is reported even if it's the last one again.
+    fn clear(&mut self) {
+        self.last_error = None;
+    }
+}
+
+struct ClockState {
+    base_ms: i64,
+    running_since: Option<Instant>,
+    speed: f32,
+}
+
+#[derive(Clone)]
+/// Tracks the playback position that frames are presented against. Clones share the same clock, so the
+/// audio thread can follow it without waiting on the ui.
+struct PlaybackClock {
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl PlaybackClock {
+    fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ClockState {
+                base_ms: 0,
+                running_since: None,
+                speed: 1.,
+            })),
+        }
+    }
+    fn elapsed_ms(&self) -> i64 {
+        self.state.lock().elapsed_ms()
+    }
+    fn set_speed(&self, speed: f32) {
+        let mut state = self.state.lock();
+        let elapsed_ms = state.elapsed_ms();
+        state.set_elapsed_ms(elapsed_ms);
+        state.speed = speed;
+    }
+    fn set_running(&self, running: bool) {
+        let mut state = self.state.lock();
+        if running && state.running_since.is_none() {
+            state.running_since = Some(Instant::now());
+        } else if !running {
+            state.base_ms = state.elapsed_ms();
+            state.running_since = None;
+        }
+    }
+    fn set_elapsed_ms(&self, elapsed_ms: i64) {
+        self.state.lock().set_elapsed_ms(elapsed_ms);
+    }
+}
+
+impl ClockState {
+    fn elapsed_ms(&self) -> i64 {
+        self.base_ms
+            + self
+                .running_since
+                .map(|t| (t.elapsed().as_millis() as f64 * self.speed as f64) as i64)
+                .unwrap_or(0)
+    }
+    fn set_elapsed_ms(&mut self, elapsed_ms: i64) {
+        self.base_ms = elapsed_ms;
+        if self.running_since.is_some() {
+            self.running_since = Some(Instant::now());
+        }
+    }
 }
 
 /// Streams audio.
 pub struct AudioStreamer {
-    _video_elapsed_ms: Cache<i64>,
+    clock: PlaybackClock,
     audio_elapsed_ms: Cache<i64>,
+    read_until_ms: Cache<i64>,
+    ended: Cache<bool>,
+    audio_clock_ms: Cache<Option<i64>>,
+    queued_until_ms: i64,
+    output_rate: u32,
+    output_channels: usize,
+    sync_mode: SyncMode,
+    sync_tolerance_ms: i64,
+    playback_speed: Cache<f32>,
+    tempo_filter: Option<TempoFilter>,
+    channel_mixing: ChannelMixing,
     audio_stream_index: usize,
     audio_decoder: ffmpeg::decoder::Audio,
+    time_base: Rational,
+    start_time: i64,
     resampler: software::resampling::Context,
     audio_sample_producer: AudioSampleProducer,
-    input_context: Input,
+    /// Resampled samples that didn't fit in the sample buffer yet. They're pushed before anything else is
+    /// decoded.
+    pending_samples: Vec<f32>,
+    /// Where the pending samples start, if they're ahead of the playback clock and have to wait for it.
+    pending_start_ms: Option<i64>,
+    stream_handle: Arc<AudioStreamHandle>,
+    input_context: MediaInput,
     player_state: Cache<PlayerState>,
 }
 
+/// Changes the tempo of decoded audio without changing its pitch.
+struct TempoFilter {
+    graph: ffmpeg::filter::Graph,
+    speed: f32,
+}
+
+impl TempoFilter {
+    fn new(decoder: &ffmpeg::decoder::Audio, speed: f32) -> Result<Self> {
+        let graph = Self::build_graph(
+            decoder.rate(),
+            decoder.format(),
+            decoder.channel_layout(),
+            speed,
+        )
+        .map_err(|e| {
+            Error::UnsupportedFormat(format!("failed to create audio tempo filter: {e}"))
+        })?;
+        Ok(Self { graph, speed })
+    }
+    /// Build the filter graph for audio of the decoder's `rate`, `format` and `channel_layout`.
+    fn build_graph(
+        rate: u32,
+        format: ffmpeg::format::Sample,
+        channel_layout: ChannelLayout,
+        speed: f32,
+    ) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
+        let mut graph = ffmpeg::filter::Graph::new();
+        let args = format!(
+            "time_base=1/{rate}:sample_rate={rate}:sample_fmt={}:channel_layout=0x{:x}",
+            format.name(),
+            channel_layout.bits()
+        );
+        let abuffer = ffmpeg::filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let abuffersink =
+            ffmpeg::filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+        graph.add(&abuffer, "in", &args)?;
+        graph.add(&abuffersink, "out", "")?;
+        if let Some(mut out) = graph.get("out") {
+            // the resampler expects the same format the decoder outputs
+            out.set_sample_format(format);
+            out.set_channel_layout(channel_layout);
+            out.set_sample_rate(rate);
+        }
+
+        let spec = atempo_factors(speed)
+            .iter()
+            .map(|factor| format!("atempo={factor}"))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        graph.output("in", 0)?.input("out", 0)?.parse(&spec)?;
+        graph.validate()?;
+        Ok(graph)
+    }
+    /// Feed a frame through the filter, returning any frames that come out the other side.
+    fn filter(&mut self, frame: &Audio) -> Result<Vec<Audio>> {
+        self.graph
+            .get("in")
+            .ok_or(Error::Decode(ffmpeg::Error::FilterNotFound))?
+            .source()
+            .add(frame)
+            .map_err(Error::Decode)?;
+        let mut sink = self
+            .graph
+            .get("out")
+            .ok_or(Error::Decode(ffmpeg::Error::FilterNotFound))?;
+        let mut filtered_frames = vec![];
+        let mut filtered_frame = Audio::empty();
+        while sink.sink().frame(&mut filtered_frame).is_ok() {
+            filtered_frames.push(filtered_frame);
+            filtered_frame = Audio::empty();
+        }
+        Ok(filtered_frames)
+    }
+}
+
+/// The factors of the chain of `atempo` filters that changes the tempo by `speed`. Older versions of atempo
+/// only accept factors from 0.5 to 2, so larger changes are chained.
+fn atempo_factors(speed: f32) -> Vec<f32> {
+    let mut atempo_factors = vec![];
+    let mut remaining_speed = speed;
+    while remaining_speed > 2. {
+        atempo_factors.push(2.);
+        remaining_speed /= 2.;
+    }
+    while remaining_speed < 0.5 {
+        atempo_factors.push(0.5);
+        remaining_speed /= 0.5;
+    }
+    atempo_factors.push(remaining_speed);
+    atempo_factors
+}
+
 const AV_TIME_BASE_RATIONAL: Rational = Rational(1, AV_TIME_BASE);
 const MILLISEC_TIME_BASE: Rational = Rational(1, 1000);
 
@@ -160,6 +829,22 @@ fn millisec_to_timestamp(millisec: i64, time_base: Rational) -> i64 {
     millisec.rescale(MILLISEC_TIME_BASE, time_base)
 }
 
+fn stream_start_time(stream: &ffmpeg::Stream) -> i64 {
+    match stream.start_time() {
+        AV_NOPTS_VALUE => 0,
+        start_time => start_time,
+    }
+}
+
+/// The size of a video stream's frames, as given by its codec parameters.
+fn video_stream_size(stream: &ffmpeg::Stream) -> [u32; 2] {
+    let parameters = unsafe { &*stream.parameters().as_ptr() };
+    [
+        parameters.width.max(0) as u32,
+        parameters.height.max(0) as u32,
+    ]
+}
+
 impl Player {
     /// A formatted string for displaying the duration of the video stream.
     pub fn duration_text(&mut self) -> String {
@@ -170,14 +855,32 @@ impl Player {
         )
     }
     fn reset(&mut self, start_playing: bool) {
-        self.video_streamer.lock().reset(start_playing);
-        if let Some(audio_decoder) = self.audio_streamer.as_mut() {
-            audio_decoder.lock().reset(start_playing);
+        self.pending_steps = 0;
+        if self.video_streamer.is_some() {
+            self.frame_queue.lock().clear();
+            self.video_requests.lock().push_back(VideoRequest::Reset);
+        }
+        if start_playing {
+            self.player_state.set(PlayerState::Playing);
+        }
+        self.clock.set_elapsed_ms(0);
+        if self.audio_streamer.is_some() {
+            self.audio_requests.lock().push_back(AudioRequest::Reset);
+            self.audio_elapsed_ms.set(None);
         }
     }
     fn set_state(&mut self, new_state: PlayerState) {
         self.player_state.set(new_state)
     }
+    /// Get a channel that receives the [`PlayerEvent`]s of this player from now on. Can be called
+    /// multiple times, each receiver gets every event.
+    pub fn events(&self) -> Receiver<PlayerEvent> {
+        self.events.subscribe()
+    }
+    /// The most recent error that occured during playback, if any.
+    pub fn last_error(&self) -> Option<Error> {
+        self.events.last_error.lock().clone()
+    }
     /// Pause the stream.
     pub fn pause(&mut self) {
         self.set_state(PlayerState::Paused)
@@ -190,71 +893,809 @@ impl Player {
     pub fn stop(&mut self) {
         self.set_state(PlayerState::Stopped)
     }
+    /// Seek to a location in the stream, given as a fraction (from `0` to `1`) of the stream's duration.
+    /// The video and audio streams are seeked on their own threads. [`PlayerEvent::Seeked`] is emitted once
+    /// the first frame at the new location is ready. If the player is stopped, it will be paused at the new
+    /// location.
+    pub fn seek_to_fraction(&mut self, seek_frac: f32) -> Result<()> {
+        let seek_frac = seek_frac.clamp(0., 1.);
+        self.pause_if_stopped();
+        self.last_seek_ms = Some((seek_frac as f64 * self.duration_ms as f64) as i64);
+
+        let seek_mode = self.seek_mode.get();
+        self.pending_steps = 0;
+        if self.video_streamer.is_some() {
+            self.video_requests.lock().push_back(VideoRequest::Seek {
+                seek_frac,
+                seek_mode,
+            });
+        } else if let Some(seek_ms) = self.last_seek_ms {
+            // without video frames to present, the clock jumps straight to the seek location
+            self.clock.set_elapsed_ms(seek_ms);
+            self.video_elapsed_ms.set(seek_ms);
+        }
+        if self.audio_streamer.is_some() {
+            self.audio_requests.lock().push_back(AudioRequest::Seek {
+                seek_frac,
+                seek_mode,
+            });
+            self.audio_elapsed_ms.set(None);
+        }
+        // with video, the decode thread reports the seek once it's done
+        if self.video_streamer.is_none() {
+            self.events.emit(PlayerEvent::Seeked(
+                (seek_frac as f64 * self.duration_ms as f64) as i64,
+            ));
+        }
+        self.ctx_ref.request_repaint();
+        Ok(())
+    }
+    /// Seek to a specific time in the stream. See [`Player::seek_to_fraction`].
+    /// Fails for streams with an unknown duration, where there's nothing to seek within.
+    pub fn seek_to(&mut self, time: Duration) -> Result<()> {
+        let seek_frac = self.seek_frac_of_ms(time.num_milliseconds())?;
+        self.seek_to_fraction(seek_frac)
+    }
+    /// The fraction of the stream's duration that `ms` is at.
+    fn seek_frac_of_ms(&self, ms: i64) -> Result<f32> {
+        if self.duration_ms <= 0 {
+            return Err(Error::Unavailable(
+                "the duration of the media is unknown".to_string(),
+            ));
+        }
+        Ok(ms as f32 / self.duration_ms as f32)
+    }
+    /// Seek by a number of frames (negative to seek backwards) relative to the current location,
+    /// based on the framerate of the video stream. See [`Player::seek_to_fraction`].
+    pub fn seek_frames(&mut self, frames: i64) -> Result<()> {
+        let frame_offset_ms = (frames as f64 * 1000. / self.framerate) as i64;
+        let target_ms = (self.video_elapsed_ms.get() + frame_offset_ms).clamp(0, self.duration_ms);
+        self.seek_to(Duration::milliseconds(target_ms))
+    }
+    /// Set the playback speed, from `0.25` to `4`. The pitch of the audio is preserved.
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        let speed = speed.clamp(*PLAYBACK_SPEED_RANGE.start(), *PLAYBACK_SPEED_RANGE.end());
+        self.clock.set_speed(speed);
+        self.playback_speed.set(speed);
+    }
+    /// The current playback speed.
+    pub fn playback_speed(&mut self) -> f32 {
+        self.playback_speed.get()
+    }
+    /// Show the next frame of the video stream, pausing the stream if it is playing. Audio-only media
+    /// steps by a frame of [`Player::framerate`]. Each call steps by a frame, even if several come before
+    /// the next frame has been decoded.
+    pub fn step_forward(&mut self) -> Result<()> {
+        self.pause_if_stopped();
+        self.pause();
+        if self.video_streamer.is_none() {
+            return self.seek_frames(1);
+        }
+        self.pending_steps += 1;
+        self.present_steps();
+        self.ctx_ref.request_repaint();
+        Ok(())
+    }
+    /// Present a queued frame for each pending step forward, as far as the decode thread has queued them,
+    /// and move the audio along to the last one.
+    fn present_steps(&mut self) {
+        if self.video_requests_pending() {
+            // the queued frames are about to be replaced
+            return;
+        }
+        let mut stepped_frame_ms = None;
+        while self.pending_steps > 0 {
+            let Some(frame) = self.frame_queue.lock().pop_front() else {
+                break;
+            };
+            self.pending_steps -= 1;
+            self.clock.set_elapsed_ms(frame.timestamp_ms);
+            stepped_frame_ms = Some(frame.timestamp_ms);
+            self.show_frame(frame);
+        }
+        if let Some(stepped_frame_ms) = stepped_frame_ms {
+            self.seek_audio_to_ms(stepped_frame_ms);
+        }
+    }
+    /// Show the previous frame of the video stream, pausing the stream if it is playing. Audio-only media
+    /// steps by a frame of [`Player::framerate`]. Each call steps by a frame, even if several come before
+    /// the previous frame has been decoded.
+    pub fn step_backward(&mut self) -> Result<()> {
+        self.pause_if_stopped();
+        self.pause();
+        if self.video_streamer.is_none() {
+            return self.seek_frames(-1);
+        }
+        self.last_seek_ms = None;
+        let mut video_requests = self.video_requests.lock();
+        // until the frame a request (such as the last step) went to has been shown, steps go back from that
+        // frame instead of the one on screen. the frame is queued before the request is taken off the queue
+        let request_pending = !video_requests.is_empty()
+            || self
+                .frame_queue
+                .lock()
+                .iter()
+                .any(|frame| frame.present_immediately);
+        let current_ms = (!request_pending).then(|| self.video_elapsed_ms.get_true());
+        video_requests.push_back(VideoRequest::StepBackward { current_ms });
+        Ok(())
+    }
+    /// Keep the audio stream in step with a video frame that was shown without playing up to it.
+    fn seek_audio_to_ms(&mut self, target_ms: i64) {
+        if self.audio_streamer.is_some() {
+            self.audio_requests
+                .lock()
+                .push_back(AudioRequest::SeekToMs { target_ms });
+        }
+    }
+    /// Start the stream in a paused state if it is stopped, so that it can be seeked or stepped through.
+    fn pause_if_stopped(&mut self) {
+        if self.player_state.get_updated() == PlayerState::Stopped {
+            self.reset(false);
+            self.set_state(PlayerState::Paused);
+            self.spawn_threads();
+        }
+    }
+    /// Set how precisely the player seeks.
+    pub fn set_seek_mode(&mut self, seek_mode: SeekMode) {
+        self.seek_mode.set(seek_mode)
+    }
+    /// How precisely the player seeks.
+    pub fn seek_mode(&mut self) -> SeekMode {
+        self.seek_mode.get()
+    }
+    /// Information about the media, such as its container, streams and chapters.
+    pub fn media_info(&self) -> &MediaInfo {
+        &self.media_info
+    }
+    /// Decode the frame currently shown again, at the video's native resolution. For audio-only media, this
+    /// is the cover art, which fails without any.
+    pub fn current_frame(&mut self) -> Result<ColorImage> {
+        let Some(video_track) = self.video_track else {
+            return self.cover_art.clone().ok_or_else(|| {
+                Error::Unavailable("the media has no video or cover art".to_string())
+            });
+        };
+        let snapshot_extractor = match &mut self.snapshot_extractor {
+            Some(extractor) if extractor.stream_index() == video_track => extractor,
+            snapshot_extractor => {
+                let media_source = &self.media_source;
+                snapshot_extractor.insert(FrameExtractor::with_stream(
+                    || media_source.open(),
+                    video_track,
+                )?)
+            }
+        };
+        // the first frame is shown before any is taken off the frame queue. an accurate seek to a frame's
+        // own timestamp decodes that same frame
+        let shown_frame_ms = self.shown_frame_ms.unwrap_or(0);
+        snapshot_extractor.frame_at(Duration::milliseconds(shown_frame_ms), [0, 0])
+    }
+    /// Save the frame currently shown to a file, at the video's native resolution.
+    pub fn save_frame(&mut self, path: impl AsRef<Path>, format: ImageFormat) -> Result<()> {
+        snapshot::save_image(&self.current_frame()?, path, format)
+    }
+    /// The video tracks in the media.
+    pub fn video_tracks(&self) -> &[TrackInfo] {
+        &self.video_tracks
+    }
+    /// The audio tracks in the media.
+    pub fn audio_tracks(&self) -> &[TrackInfo] {
+        &self.audio_tracks
+    }
+    /// The stream index of the video track being played, unless the media is audio-only.
+    pub fn video_track(&self) -> Option<usize> {
+        self.video_track
+    }
+    /// Whether the media has no video (cover art aside), only audio.
+    pub fn is_audio_only(&self) -> bool {
+        self.video_streamer.is_none()
+    }
+    /// The stream index of the audio track being played, if there is audio.
+    pub fn audio_track(&self) -> Option<usize> {
+        self.audio_track
+    }
+    /// Switch to another video track (by its [`TrackInfo::stream_index`]), keeping the current position.
+    pub fn select_video_track(&mut self, stream_index: usize) -> Result<()> {
+        let track_position = self
+            .video_tracks
+            .iter()
+            .position(|t| t.stream_index == stream_index)
+            .ok_or(Error::InvalidTrack(stream_index))?;
+        if self.video_streamer.is_none() {
+            return Err(Error::Unavailable("the media has no video".to_string()));
+        }
+        [self.width, self.height] = self.video_track_sizes[track_position];
+        let seek_frac = if self.player_state.get_updated() != PlayerState::Stopped {
+            self.last_seek_ms = Some(self.video_elapsed_ms.get());
+            Some(self.duration_frac())
+        } else {
+            None
+        };
+        self.video_track = Some(stream_index);
+        // the thumbnails of the new track are decoded as they're needed
+        self.thumbnails = None;
+        self.video_requests
+            .lock()
+            .push_back(VideoRequest::SelectStream {
+                stream_index,
+                seek_frac,
+                seek_mode: self.seek_mode.get(),
+            });
+        Ok(())
+    }
+    /// The subtitle tracks in the media.
+    pub fn subtitle_tracks(&self) -> &[TrackInfo] {
+        &self.subtitle_tracks
+    }
+    /// The stream index of the subtitle track being shown, if an embedded one is selected.
+    pub fn subtitle_track(&self) -> Option<usize> {
+        self.subtitle_streamer
+            .as_ref()
+            .and_then(|subtitle_streamer| subtitle_streamer.stream_index)
+    }
+    /// Show an embedded subtitle track (by its [`TrackInfo::stream_index`]).
+    pub fn select_subtitle_track(&mut self, stream_index: usize) -> Result<()> {
+        if !self
+            .subtitle_tracks
+            .iter()
+            .any(|t| t.stream_index == stream_index)
+        {
+            return Err(Error::InvalidTrack(stream_index));
+        }
+        let subtitle_input = self.media_source.open()?;
+        self.subtitle_streamer = Some(SubtitleStreamer::spawn(
+            subtitle_input,
+            Some(stream_index),
+            self.events.clone(),
+        )?);
+        Ok(())
+    }
+    /// Show subtitles from an external file (SRT, WebVTT, ASS, VobSub, or anything else ffmpeg can read
+    /// subtitles from), replacing the current subtitle track.
+    pub fn load_subtitle_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.load_subtitles(path.as_ref().to_path_buf())
+    }
+    /// Like [`Player::load_subtitle_file`], but for any [`MediaSource`].
+    pub fn load_subtitles(&mut self, media_source: impl MediaSource) -> Result<()> {
+        let subtitle_input = media_source.open()?;
+        self.subtitle_streamer = Some(SubtitleStreamer::spawn(
+            subtitle_input,
+            None,
+            self.events.clone(),
+        )?);
+        Ok(())
+    }
+    /// Stop showing subtitles.
+    pub fn clear_subtitles(&mut self) {
+        self.subtitle_streamer = None;
+    }
+    /// Delay the subtitles by an offset (negative to show them earlier).
+    pub fn set_subtitle_offset(&mut self, offset: Duration) {
+        self.subtitle_offset_ms = offset.num_milliseconds();
+    }
+    /// How much the subtitles are delayed by.
+    pub fn subtitle_offset(&self) -> Duration {
+        Duration::milliseconds(self.subtitle_offset_ms)
+    }
+    /// Show or hide the subtitles, without unloading them.
+    pub fn set_subtitles_visible(&mut self, visible: bool) {
+        self.subtitles_visible = visible;
+    }
+    /// Whether subtitles are shown.
+    pub fn subtitles_visible(&self) -> bool {
+        self.subtitles_visible
+    }
+    /// The position of the subtitles, in ms: the playback position, less the subtitle offset.
+    fn subtitle_position_ms(&mut self) -> i64 {
+        self.video_elapsed_ms.get() - self.subtitle_offset_ms
+    }
+    fn render_subtitles(&mut self, ui: &mut Ui, rect: Rect) {
+        let position_ms = self.subtitle_position_ms();
+        if let Some(subtitle_streamer) = self.subtitle_streamer.as_ref() {
+            // hidden subtitles are still read, so they're ready once shown
+            subtitle_streamer.set_position_ms(position_ms);
+            if !self.subtitles_visible {
+                return;
+            }
+            subtitle::paint_subtitles(
+                ui,
+                rect,
+                &subtitle_streamer.subtitles_at(position_ms),
+                [self.width, self.height],
+                &mut self.subtitle_textures,
+            );
+        }
+    }
+    /// Switch to another audio track (by its [`TrackInfo::stream_index`]), keeping the current position.
+    /// The track is switched on the audio thread, which reports any error through [`Player::events`].
+    /// Requires audio to have been initialized with [`Player::with_audio`].
+    pub fn select_audio_track(&mut self, stream_index: usize) -> Result<()> {
+        if !self
+            .audio_tracks
+            .iter()
+            .any(|t| t.stream_index == stream_index)
+        {
+            return Err(Error::InvalidTrack(stream_index));
+        }
+        if self.audio_streamer.is_none() {
+            return Err(Error::Unavailable(
+                "audio has not been initialized with `with_audio`".to_string(),
+            ));
+        }
+        let seek_ms = if self.player_state.get_updated() != PlayerState::Stopped {
+            Some(self.video_elapsed_ms.get())
+        } else {
+            None
+        };
+        self.audio_track = Some(stream_index);
+        self.audio_requests
+            .lock()
+            .push_back(AudioRequest::SelectStream {
+                stream_index,
+                seek_ms,
+            });
+        Ok(())
+    }
+    /// Set the gain of the audio in its mixer, on top of [`Player::audio_volume`], to balance it against
+    /// other players. Does nothing without audio.
+    pub fn set_audio_gain(&self, gain: f32) {
+        if let Some(stream_handle) = self.audio_stream_handle.as_ref() {
+            stream_handle.set_gain(gain);
+        }
+    }
+    /// The gain of the audio in its mixer.
+    pub fn audio_gain(&self) -> f32 {
+        self.audio_stream_handle
+            .as_ref()
+            .map_or(1., |stream_handle| stream_handle.gain())
+    }
+    /// Set the pan of the audio, from `-1` (left) to `1` (right). Does nothing without audio.
+    pub fn set_audio_pan(&self, pan: f32) {
+        if let Some(stream_handle) = self.audio_stream_handle.as_ref() {
+            stream_handle.set_pan(pan);
+        }
+    }
+    /// The pan of the audio.
+    pub fn audio_pan(&self) -> f32 {
+        self.audio_stream_handle
+            .as_ref()
+            .map_or(0., |stream_handle| stream_handle.pan())
+    }
+    /// Mute or unmute the audio in its mixer, keeping [`Player::audio_volume`]. Does nothing without audio.
+    pub fn set_audio_muted(&self, muted: bool) {
+        if let Some(stream_handle) = self.audio_stream_handle.as_ref() {
+            stream_handle.set_muted(muted);
+        }
+    }
+    /// Whether the audio is muted in its mixer.
+    pub fn audio_muted(&self) -> bool {
+        self.audio_stream_handle
+            .as_ref()
+            .map_or(false, |stream_handle| stream_handle.is_muted())
+    }
+    /// The peak level of each output channel of the audio, from `0` to `1`, falling off over a few hundred
+    /// milliseconds (see [`AudioStreamHandle::peak_levels`]). Empty without audio.
+    pub fn audio_peak_levels(&self) -> Vec<f32> {
+        self.audio_stream_handle
+            .as_ref()
+            .map(|stream_handle| stream_handle.peak_levels())
+            .unwrap_or_default()
+    }
+    /// The channel layout of the audio track being played, if there is audio.
+    pub fn audio_channel_layout(&self) -> Option<ChannelLayout> {
+        self.audio_streamer
+            .as_ref()
+            .map(|audio_streamer| audio_streamer.lock().resampler.input().channel_layout)
+    }
+    /// How the audio channels are mixed into the output's channels.
+    pub fn channel_mixing(&self) -> &ChannelMixing {
+        &self.channel_mixing
+    }
+    /// Change how the audio channels are mixed into the output's channels (downmix levels, channel
+    /// mapping, muted and soloed channels). Applies to every audio track.
+    pub fn set_channel_mixing(&mut self, channel_mixing: ChannelMixing) -> Result<()> {
+        if let Some(audio_streamer) = self.audio_streamer.as_ref() {
+            audio_streamer
+                .lock()
+                .set_channel_mixing(channel_mixing.clone())?;
+        }
+        self.channel_mixing = channel_mixing;
+        Ok(())
+    }
+    /// Mute or unmute a channel of the audio (such as [`ChannelLayout::LOW_FREQUENCY`]).
+    pub fn set_channel_muted(&mut self, channel: ChannelLayout, muted: bool) -> Result<()> {
+        let mut channel_mixing = self.channel_mixing.clone();
+        channel_mixing.muted_channels.set(channel, muted);
+        self.set_channel_mixing(channel_mixing)
+    }
+    /// Solo or unsolo a channel of the audio. While any channel is soloed, only soloed channels are heard.
+    pub fn set_channel_soloed(&mut self, channel: ChannelLayout, soloed: bool) -> Result<()> {
+        let mut channel_mixing = self.channel_mixing.clone();
+        channel_mixing.soloed_channels.set(channel, soloed);
+        self.set_channel_mixing(channel_mixing)
+    }
     fn duration_frac(&mut self) -> f32 {
-        self.video_elapsed_ms.get() as f32 / self.duration_ms as f32
-    }
-    fn spawn_timers(&mut self) {
-        let mut texture_handle = self.texture_handle.clone();
-        let texture_options = self.texture_options;
-        let ctx = self.ctx_ref.clone();
-        let stream_decoder = Arc::clone(&self.video_streamer);
-        let wait_duration = Duration::milliseconds((1000. / self.framerate) as i64);
+        if self.duration_ms > 0 {
+            self.video_elapsed_ms.get() as f32 / self.duration_ms as f32
+        } else {
+            0.
+        }
+    }
+    fn spawn_threads(&mut self) {
         let duration_ms = self.duration_ms;
-        let frame_timer_guard = self.frame_timer.schedule_repeating(wait_duration, move || {
-            ctx.request_repaint();
-            stream_decoder
-                .lock()
-                .process_state(duration_ms, true, |frame| {
-                    texture_handle.set(frame, texture_options)
-                });
-        });
-        self.frame_thread = Some(frame_timer_guard);
-
-        if let Some(audio_decoder) = self.audio_streamer.as_ref() {
-            let audio_decoder = Arc::clone(audio_decoder);
-            let audio_timer_guard =
-                self.audio_timer
-                    .schedule_repeating(Duration::zero(), move || {
-                        audio_decoder
-                            .lock()
-                            .process_state(duration_ms, false, |_| {});
-                    });
-            self.audio_thread = Some(audio_timer_guard);
+        // audio-only media has nothing to decode ahead
+        if let Some(video_streamer) = self.video_streamer.clone() {
+            let ctx = self.ctx_ref.clone();
+            let mut seek_mode = self.seek_mode.clone();
+            let frame_queue_capacity = self.config.frame_queue_capacity.max(1);
+            let events = self.events.clone();
+            let video_requests = Arc::clone(&self.video_requests);
+            let audio_requests = self
+                .audio_streamer
+                .is_some()
+                .then(|| Arc::clone(&self.audio_requests));
+            let alive = Arc::new(AtomicBool::new(true));
+            let thread_alive = Arc::clone(&alive);
+            let handle = std::thread::spawn(move || {
+                let mut last_seek_frac = None;
+                let mut errors = ThreadErrors::default();
+                while thread_alive.load(Ordering::Relaxed) {
+                    let mut video_streamer = video_streamer.lock();
+                    let video_request = video_requests.lock().front().copied();
+                    let result = match (video_request, video_streamer.player_state.get()) {
+                        (Some(video_request), _) => {
+                            let result = video_streamer.handle_request(
+                                video_request,
+                                duration_ms,
+                                audio_requests.as_ref(),
+                            );
+                            // reported once the first frame at the seek location is queued
+                            if let (Ok(()), VideoRequest::Seek { seek_frac, .. }) =
+                                (&result, video_request)
+                            {
+                                events.emit(PlayerEvent::Seeked(
+                                    (seek_frac as f64 * duration_ms as f64) as i64,
+                                ));
+                            }
+                            // the ui doesn't present anything until the request is done
+                            video_requests.lock().pop_front();
+                            result.map(|()| true)
+                        }
+                        (None, PlayerState::Seeking(seek_frac))
+                            if last_seek_frac != Some(seek_frac) =>
+                        {
+                            last_seek_frac = Some(seek_frac);
+                            video_streamer
+                                .seek_into_queue(seek_frac, duration_ms, seek_mode.get())
+                                .map(|()| true)
+                        }
+                        (
+                            None,
+                            PlayerState::Playing | PlayerState::Paused | PlayerState::Buffering,
+                        ) => {
+                            last_seek_frac = None;
+                            let queue_len = video_streamer.frame_queue.lock().len();
+                            if queue_len < frame_queue_capacity {
+                                match video_streamer.decode_into_queue() {
+                                    Ok(()) => Ok(true),
+                                    Err(e) if e.is_eof() => Ok(false),
+                                    Err(e) => Err(e),
+                                }
+                            } else {
+                                Ok(false)
+                            }
+                        }
+                        _ => Ok(false),
+                    };
+                    drop(video_streamer);
+                    match result {
+                        Ok(true) => {
+                            errors.clear();
+                            ctx.request_repaint();
+                        }
+                        Ok(false) => std::thread::sleep(DECODE_THREAD_IDLE_WAIT),
+                        Err(e) => std::thread::sleep(errors.report(&events, e)),
+                    }
+                }
+            });
+            self.decode_thread = Some(DecodeThread {
+                alive,
+                handle: Some(handle),
+            });
+        }
+
+        if let Some(audio_streamer) = self.audio_streamer.clone() {
+            let audio_requests = Arc::clone(&self.audio_requests);
+            let mut seek_mode = self.seek_mode.clone();
+            let events = self.events.clone();
+            let alive = Arc::new(AtomicBool::new(true));
+            let thread_alive = Arc::clone(&alive);
+            let handle = std::thread::spawn(move || {
+                let mut last_seek_frac = None;
+                let mut errors = ThreadErrors::default();
+                while thread_alive.load(Ordering::Relaxed) {
+                    let mut audio_streamer = audio_streamer.lock();
+                    let audio_request = audio_requests.lock().front().copied();
+                    let result = if let Some(audio_request) = audio_request {
+                        let result = audio_streamer.handle_request(audio_request, duration_ms);
+                        audio_requests.lock().pop_front();
+                        result.map(|()| false)
+                    } else {
+                        audio_streamer.decode_step(
+                            duration_ms,
+                            seek_mode.get(),
+                            &mut last_seek_frac,
+                        )
+                    };
+                    // the lock is released before waiting, so the ui is never held up by it
+                    drop(audio_streamer);
+                    match result {
+                        Ok(false) => errors.clear(),
+                        Ok(true) => std::thread::sleep(AUDIO_THREAD_IDLE_WAIT),
+                        Err(e) => std::thread::sleep(errors.report(&events, e)),
+                    }
+                }
+            });
+            self.audio_thread = Some(DecodeThread {
+                alive,
+                handle: Some(handle),
+            });
         }
     }
     /// Start the stream.
     pub fn start(&mut self) {
-        self.frame_thread = None;
+        self.decode_thread = None;
         self.audio_thread = None;
+        self.first_frame_pending = true;
         self.reset(true);
-        self.spawn_timers();
+        self.spawn_threads();
+    }
+
+    /// Move the playback clock to the audio that is currently being heard (or to the external clock), if
+    /// that is the master clock and the playback clock has drifted too far from it.
+    fn sync_clock(&mut self, player_state: PlayerState) {
+        if self.sync_mode() == SyncMode::External {
+            if let (PlayerState::Playing, Some(external_clock)) =
+                (player_state, self.external_clock.as_ref())
+            {
+                let external_clock_ms = external_clock().num_milliseconds();
+                let drift_ms = external_clock_ms - self.clock.elapsed_ms();
+                if drift_ms.abs() > self.config.sync_tolerance_ms {
+                    self.clock.set_elapsed_ms(external_clock_ms);
+                }
+            }
+            return;
+        }
+        if self.audio_streamer.is_none() || self.sync_mode() != SyncMode::AudioMaster {
+            return;
+        }
+        if let PlayerState::Seeking(_) = player_state {
+            // the audio clock is stale until the audio stream catches up with the seek
+            self.audio_elapsed_ms.set(None);
+        } else if player_state == PlayerState::Playing {
+            if let Some(audio_clock_ms) = self.audio_elapsed_ms.get() {
+                let drift_ms = audio_clock_ms - self.clock.elapsed_ms();
+                if drift_ms.abs() > self.config.sync_tolerance_ms {
+                    self.clock.set_elapsed_ms(audio_clock_ms);
+                }
+            }
+        }
+    }
+
+    /// How the audio and video are kept in sync. Audio-only media follows the audio, unless it follows an
+    /// external clock.
+    fn sync_mode(&self) -> SyncMode {
+        if self.video_streamer.is_some() || self.config.sync_mode == SyncMode::External {
+            self.config.sync_mode
+        } else {
+            SyncMode::AudioMaster
+        }
+    }
+
+    /// Set the clock followed with [`SyncMode::External`]: it returns where in the media the player should
+    /// be. The player jumps to it whenever it drifts further than [`PlayerConfig::sync_tolerance_ms`]
+    /// while playing, so seeking, pausing and changing speed are up to the clock's owner.
+    pub fn set_external_clock(&mut self, external_clock: impl Fn() -> Duration + Send + 'static) {
+        self.external_clock = Some(Box::new(external_clock));
+    }
+
+    /// Follow the playback clock without any video frames to present, for audio-only media. Returns how
+    /// long until the next redraw is due.
+    fn present_audio_only(&mut self, player_state: PlayerState) -> Option<i64> {
+        if let PlayerState::Seeking(seek_frac) = player_state {
+            self.clock
+                .set_elapsed_ms((seek_frac as f64 * self.duration_ms as f64) as i64);
+        }
+        let elapsed_ms = if self.duration_ms > 0 {
+            self.clock.elapsed_ms().min(self.duration_ms)
+        } else {
+            self.clock.elapsed_ms()
+        };
+        self.video_elapsed_ms.set(elapsed_ms);
+        if player_state == PlayerState::Playing
+            && self.audio_streamer.is_none()
+            && self.duration_ms > 0
+            && elapsed_ms >= self.duration_ms
+        {
+            // without an audio stream, nothing else reaches the end of the file
+            self.player_state.set(PlayerState::EndOfFile);
+        }
+        if is_audible_state(player_state) {
+            if let (Some(visualizer), Some(stream_handle)) =
+                (self.visualizer.as_mut(), self.audio_stream_handle.as_ref())
+            {
+                visualizer.push_levels(&stream_handle.peak_levels());
+            }
+        }
+        Some((1000. / self.framerate) as i64)
+    }
+
+    /// Present the latest frame from the frame queue that is due according to the playback clock.
+    /// Returns how long until the next queued frame is due, if there is one.
+    fn present_frame(&mut self) -> Option<i64> {
+        let mut frame_queue = self.frame_queue.lock();
+        let mut presented_frame = None;
+        while let Some(frame) = frame_queue.front() {
+            if frame.present_immediately {
+                self.clock.set_elapsed_ms(frame.timestamp_ms);
+            } else if frame.timestamp_ms > self.clock.elapsed_ms() {
+                break;
+            }
+            presented_frame = frame_queue.pop_front();
+        }
+        let next_frame_wait_ms = frame_queue
+            .front()
+            .map(|frame| (frame.timestamp_ms - self.clock.elapsed_ms()).max(0));
+        drop(frame_queue);
+
+        if let Some(frame) = presented_frame {
+            self.show_frame(frame);
+        } else if next_frame_wait_ms.is_none()
+            && self.player_state.get() == PlayerState::Playing
+            && !self.buffer_underrun
+        {
+            // the next frame is overdue, and has not been decoded yet
+            let frame_duration_ms = (1000. / self.framerate) as i64;
+            if self.clock.elapsed_ms() > self.video_elapsed_ms.get_true() + frame_duration_ms {
+                self.buffer_underrun = true;
+                self.events.emit(PlayerEvent::BufferUnderrun);
+            }
+        }
+        next_frame_wait_ms
+    }
+
+    /// Show a frame taken off the frame queue.
+    fn show_frame(&mut self, frame: QueuedFrame) {
+        self.video_elapsed_ms.set(frame.timestamp_ms);
+        self.shown_frame_ms = Some(frame.timestamp_ms);
+        self.texture_handle.set(frame.image, self.texture_options);
+        self.buffer_underrun = false;
+        if self.first_frame_pending {
+            self.first_frame_pending = false;
+            self.events.emit(PlayerEvent::FirstFrameReady);
+        }
+    }
+
+    /// Whether the decode thread has requests left to handle.
+    fn video_requests_pending(&self) -> bool {
+        !self.video_requests.lock().is_empty()
+    }
+
+    /// Resume playback once the frame queue has been refilled after running dry.
+    fn process_buffering(&mut self) {
+        let frame_queue = self.frame_queue.lock();
+        if frame_queue.len() >= self.config.frame_queue_capacity.max(1) {
+            // continue from the first buffered frame, instead of skipping what was missed while buffering
+            if let Some(frame) = frame_queue.front() {
+                self.clock.set_elapsed_ms(frame.timestamp_ms);
+            }
+            drop(frame_queue);
+            self.buffer_underrun = false;
+            self.player_state.set(PlayerState::Playing);
+        }
+    }
+
+    /// The timestamp (in ms) up to which the media has been read ahead of playback, whether or not it has
+    /// been decoded yet.
+    pub fn buffered_until_ms(&mut self) -> i64 {
+        let mut read_until_ms = self.video_elapsed_ms.get();
+        if self.video_streamer.is_some() {
+            read_until_ms = read_until_ms.max(self.video_read_until_ms.get_updated());
+        }
+        if self.audio_streamer.is_some() {
+            read_until_ms = read_until_ms.max(self.audio_read_until_ms.get_updated());
+        }
+        read_until_ms
+    }
+
+    /// Whether the stream that playback follows (the video, or the audio of audio-only media) has been
+    /// decoded to its end.
+    fn stream_ended(&mut self) -> bool {
+        if self.video_streamer.is_some() {
+            // a seek or reset waiting for the decode thread is about to move the stream away from its end
+            !self.video_requests_pending() && self.video_ended.get_updated()
+        } else {
+            self.audio_streamer.is_some()
+                && self.audio_requests.lock().is_empty()
+                && self.audio_ended.get_updated()
+        }
     }
 
     fn process_state(&mut self) {
         let mut reset_stream = false;
-        let video_elapsed_ms = self.video_elapsed_ms.get();
-        if self.last_seek_ms.is_some() {
-            let last_seek_ms = *self.last_seek_ms.as_ref().unwrap();
-            if self.video_elapsed_ms.get_true() > last_seek_ms || video_elapsed_ms == 0 {
-                self.video_elapsed_ms.override_value = None;
-                self.last_seek_ms = None;
-            } else {
-                self.video_elapsed_ms.override_value = Some(last_seek_ms);
-            }
+        let mut player_state = self.player_state.get_updated();
+        // the end of the file is reached once the frames decoded before the end have been presented
+        if matches!(player_state, PlayerState::Playing | PlayerState::Buffering)
+            && self.stream_ended()
+        {
+            player_state = PlayerState::EndOfFile;
+            self.player_state.set(player_state);
+        }
+        let awaiting_seek_preview = self.video_requests_pending()
+            || self
+                .frame_queue
+                .lock()
+                .iter()
+                .any(|frame| frame.present_immediately);
+        // show the seek location until the frame at the seek location has been presented
+        if matches!(player_state, PlayerState::Seeking(_)) || awaiting_seek_preview {
+            self.video_elapsed_ms.override_value = self.last_seek_ms;
         } else {
             self.video_elapsed_ms.override_value = None;
+            self.last_seek_ms = None;
         }
 
-        match self.player_state.get_updated() {
+        self.clock.set_running(matches!(
+            player_state,
+            PlayerState::Playing | PlayerState::EndOfFile
+        ));
+        self.sync_clock(player_state);
+        if player_state != PlayerState::Paused {
+            self.pending_steps = 0;
+        } else if self.pending_steps > 0 {
+            // steps that came before their frames were decoded
+            self.present_steps();
+        }
+        let next_frame_wait_ms = if self.video_streamer.is_none() {
+            self.present_audio_only(player_state)
+        } else if self.video_requests_pending() {
+            // the queued frames are about to be replaced, the decode thread repaints once they are
+            None
+        } else if player_state == PlayerState::Buffering {
+            self.process_buffering();
+            None
+        } else {
+            self.present_frame()
+        };
+        if player_state == PlayerState::Playing && self.buffer_underrun {
+            self.player_state.set(PlayerState::Buffering);
+        }
+
+        match player_state {
             PlayerState::EndOfFile => {
-                if self.config.looping {
-                    reset_stream = true;
-                } else {
-                    self.player_state.set(PlayerState::Stopped);
+                // the decode thread reaches the end of the file before the queued frames are shown
+                let frames_left =
+                    !self.frame_queue.lock().is_empty() || self.video_requests_pending();
+                // audio-only media ends once the queued audio has been heard
+                let audio_left = self.video_streamer.is_none()
+                    && self
+                        .audio_streamer
+                        .as_ref()
+                        .map_or(false, |audio_streamer| {
+                            audio_streamer.lock().has_queued_samples()
+                        });
+                if !frames_left && !audio_left {
+                    self.events.emit(PlayerEvent::EndOfFile);
+                    if self.config.looping {
+                        reset_stream = true;
+                    } else {
+                        self.player_state.set(PlayerState::Stopped);
+                    }
                 }
             }
             PlayerState::Stopped => {
-                self.frame_thread = None;
+                self.decode_thread = None;
                 self.audio_thread = None;
             }
             _ => (),
@@ -262,6 +1703,35 @@ impl Player {
 
         if reset_stream {
             self.reset(true);
+            self.events.emit(PlayerEvent::Looped);
+        }
+
+        let player_state = self.player_state.get();
+        if std::mem::discriminant(&player_state)
+            != std::mem::discriminant(&self.reported_player_state)
+        {
+            self.reported_player_state = player_state;
+            if let Some(stream_handle) = self.audio_stream_handle.as_ref() {
+                // fade the audio out (holding on to what's queued) or back in
+                stream_handle.set_playing(is_audible_state(player_state));
+            }
+            if let (PlayerState::Stopped, Some(audio_streamer)) =
+                (player_state, self.audio_streamer.as_ref())
+            {
+                audio_streamer.lock().discard_queued_samples();
+            }
+            self.events.emit(PlayerEvent::StateChanged(player_state));
+        }
+
+        if let Some(wait_ms) = next_frame_wait_ms {
+            if matches!(
+                self.player_state.get(),
+                PlayerState::Playing | PlayerState::EndOfFile
+            ) {
+                let wait_ms = wait_ms as f32 / self.playback_speed.get();
+                self.ctx_ref
+                    .request_repaint_after(std::time::Duration::from_millis(wait_ms as u64));
+            }
         }
     }
 
@@ -270,6 +1740,7 @@ impl Player {
         self.process_state();
         let image = Image::new(self.texture_handle.id(), size).sense(Sense::click());
         let response = ui.add(image);
+        self.render_overlays(ui, response.rect);
         if self.config.show_controls {
             self.render_ui(ui, &response);
         }
@@ -281,12 +1752,91 @@ impl Player {
         self.process_state();
         let image = Image::new(self.texture_handle.id(), rect.size()).sense(Sense::click());
         let response = ui.put(rect, image);
+        self.render_overlays(ui, response.rect);
         if self.config.show_controls {
             self.render_ui(ui, &response);
         }
         response
     }
 
+    /// Draw what's shown over the video: the visualizer, the subtitles and the buffering spinner.
+    fn render_overlays(&mut self, ui: &mut Ui, rect: Rect) {
+        if let Some(visualizer) = self.visualizer.as_ref() {
+            visualizer.paint(ui, rect);
+        }
+        self.render_subtitles(ui, rect);
+        if self.player_state.get() == PlayerState::Buffering {
+            paint_spinner(ui, rect);
+        }
+    }
+
+    /// Draw a thumbnail of the video at `seek_frac` with its timestamp, floating above the seekbar.
+    fn render_seekbar_preview(
+        &mut self,
+        ui: &mut Ui,
+        playback_rect: Rect,
+        pointer_x: f32,
+        seekbar_top: f32,
+        seek_frac: f32,
+    ) {
+        let Some(video_track) = self.video_track else {
+            return;
+        };
+        let thumbnails = self.thumbnails.get_or_insert_with(|| {
+            ThumbnailCache::new(
+                &self.ctx_ref,
+                Arc::clone(&self.media_source),
+                video_track,
+                self.duration_ms,
+                SEEKBAR_PREVIEW_WIDTH,
+            )
+        });
+        let thumbnail = thumbnails.thumbnail_at(seek_frac);
+        let preview_ms = (seek_frac as f64 * self.duration_ms as f64) as i64;
+
+        let preview_margin = 15.;
+        let preview_text_height = 18.;
+        let image_size = thumbnail
+            .as_ref()
+            .map(|texture| {
+                let [width, height] = texture.size();
+                vec2(width as f32, height as f32)
+            })
+            .unwrap_or(vec2(60., 0.));
+        let preview_size = image_size + vec2(0., preview_text_height);
+        let preview_left = (pointer_x - preview_size.x / 2.)
+            .min(playback_rect.right() - preview_size.x)
+            .max(playback_rect.left());
+        let preview_rect = Rect::from_min_size(
+            pos2(preview_left, seekbar_top - preview_margin - preview_size.y),
+            preview_size,
+        );
+
+        ui.painter().rect_filled(
+            preview_rect.expand(2.),
+            Rounding::same(3.),
+            Color32::from_black_alpha(200),
+        );
+        if let Some(texture) = thumbnail.as_ref() {
+            ui.painter().image(
+                texture.id(),
+                Rect::from_min_size(preview_rect.min, image_size),
+                Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+                Color32::WHITE,
+            );
+        }
+        ui.painter().text(
+            preview_rect.center_bottom() - vec2(0., preview_text_height / 2.),
+            Align2::CENTER_CENTER,
+            format_duration(Duration::milliseconds(preview_ms)),
+            FontId {
+                size: 12.,
+                ..Default::default()
+            },
+            Color32::WHITE,
+        );
+    }
+
     fn render_ui(&mut self, ui: &mut Ui, playback_response: &Response) -> Option<Rect> {
         let hovered = ui.rect_contains_pointer(playback_response.rect);
         let currently_seeking = matches!(self.player_state.get(), PlayerState::Seeking(_));
@@ -315,7 +1865,9 @@ impl Player {
                     PlayerState::Stopped => start_stream = true,
                     PlayerState::EndOfFile => reset_stream = true,
                     PlayerState::Paused => self.player_state.set(PlayerState::Playing),
-                    PlayerState::Playing => self.player_state.set(PlayerState::Paused),
+                    PlayerState::Playing | PlayerState::Buffering => {
+                        self.player_state.set(PlayerState::Paused)
+                    }
                     _ => (),
                 }
 
@@ -357,10 +1909,22 @@ impl Player {
                         .max(0.)
                         .min(fullseekbar_width)
                         / fullseekbar_width;
+                    if self.config.show_seekbar_previews
+                        && self.duration_ms > 0
+                        && self.video_streamer.is_some()
+                    {
+                        self.render_seekbar_preview(
+                            ui,
+                            playback_response.rect,
+                            hover_pos.x,
+                            fullseekbar_rect.top(),
+                            seek_frac,
+                        );
+                    }
                     if ui.ctx().input(|i| i.pointer.primary_down()) {
                         if is_stopped {
                             self.reset(true);
-                            self.spawn_timers();
+                            self.spawn_threads();
                         }
                         if !currently_seeking {
                             self.preseek_player_state = Some(self.player_state.get_updated());
@@ -375,6 +1939,12 @@ impl Player {
                                 .max(fullseekbar_rect.left()),
                         );
                     } else if ui.ctx().input(|i| i.pointer.any_released()) {
+                        // the seek is finished like any other, which reports it once it's done
+                        if currently_seeking {
+                            if let Err(e) = self.seek_to_fraction(seek_frac) {
+                                self.events.emit_error(e);
+                            }
+                        }
                         if let Some(previous_state) = self.preseek_player_state.take() {
                             self.set_state(previous_state)
                         } else {
@@ -422,6 +1992,10 @@ impl Player {
                 size: 14.,
                 ..Default::default()
             };
+            let speed_text_font_id = FontId {
+                size: 12.,
+                ..Default::default()
+            };
 
             let mut shadow = Shadow::big_light();
             shadow.color = shadow.color.linear_multiply(seekbar_anim_frac);
@@ -440,6 +2014,15 @@ impl Player {
                 Rounding::none(),
                 fullseekbar_color.linear_multiply(0.5),
             );
+            if !currently_seeking && self.duration_ms > 0 {
+                let buffered_frac =
+                    (self.buffered_until_ms() as f32 / self.duration_ms as f32).clamp(0., 1.);
+                let mut buffered_rect = fullseekbar_rect;
+                buffered_rect
+                    .set_right(fullseekbar_rect.left() + fullseekbar_width * buffered_frac);
+                ui.painter()
+                    .rect_filled(buffered_rect, Rounding::none(), fullseekbar_color);
+            }
             ui.painter()
                 .rect_filled(seekbar_rect, Rounding::none(), seekbar_color);
             ui.painter().text(
@@ -466,6 +2049,272 @@ impl Player {
                 );
             }
 
+            let speed_text_offset = if self.audio_streamer.is_some() {
+                vec2(-30., text_y_offset)
+            } else {
+                vec2(-5., text_y_offset)
+            };
+            let speed_text_pos = fullseekbar_rect.right_top() + speed_text_offset;
+            let speed_text_rect = ui.painter().text(
+                speed_text_pos,
+                Align2::RIGHT_BOTTOM,
+                format!("{}x", self.playback_speed.get()),
+                speed_text_font_id.clone(),
+                text_color,
+            );
+            let speed_menu_id = playback_response.id.with("speed_menu");
+            let mut speed_menu_open: bool = ui
+                .ctx()
+                .memory_mut(|m| *m.data.get_temp_mut_or_default(speed_menu_id));
+            if ui
+                .interact(
+                    speed_text_rect,
+                    playback_response.id.with("speed_text_sense"),
+                    Sense::click(),
+                )
+                .clicked()
+            {
+                speed_menu_open = !speed_menu_open;
+            }
+            if speed_menu_open {
+                let speed_option_height = 18.;
+                let speed_option_width = 45.;
+                let speed_menu_margin = 5.;
+                let speed_menu_rect = Rect::from_min_size(
+                    speed_text_rect.right_top()
+                        - vec2(
+                            speed_option_width,
+                            speed_menu_margin + speed_option_height * PLAYBACK_SPEEDS.len() as f32,
+                        ),
+                    vec2(
+                        speed_option_width,
+                        speed_option_height * PLAYBACK_SPEEDS.len() as f32,
+                    ),
+                );
+                ui.painter().rect_filled(
+                    speed_menu_rect,
+                    Rounding::same(5.),
+                    Color32::from_black_alpha(150).linear_multiply(seekbar_anim_frac),
+                );
+                for (i, speed) in PLAYBACK_SPEEDS.iter().rev().enumerate() {
+                    let speed_option_rect = Rect::from_min_size(
+                        speed_menu_rect.left_top() + vec2(0., i as f32 * speed_option_height),
+                        vec2(speed_option_width, speed_option_height),
+                    );
+                    let speed_option_color = if *speed == self.playback_speed.get() {
+                        text_color
+                    } else {
+                        Color32::GRAY.linear_multiply(seekbar_anim_frac)
+                    };
+                    ui.painter().text(
+                        speed_option_rect.center(),
+                        Align2::CENTER_CENTER,
+                        format!("{speed}x"),
+                        speed_text_font_id.clone(),
+                        speed_option_color,
+                    );
+                    if ui
+                        .interact(
+                            speed_option_rect,
+                            speed_menu_id.with(i),
+                            Sense::click(),
+                        )
+                        .clicked()
+                    {
+                        self.set_playback_speed(*speed);
+                        speed_menu_open = false;
+                    }
+                }
+            }
+            ui.ctx()
+                .memory_mut(|m| m.data.insert_temp(speed_menu_id, speed_menu_open));
+
+            let mut controls_left = speed_text_rect.left_bottom();
+            if self.video_tracks.len() > 1
+                || self.audio_tracks.len() > 1
+                || !self.subtitle_tracks.is_empty()
+            {
+                let track_icon_pos = controls_left + vec2(-10., 0.);
+                let track_icon_rect = ui.painter().text(
+                    track_icon_pos,
+                    Align2::RIGHT_BOTTOM,
+                    "☰",
+                    speed_text_font_id.clone(),
+                    text_color,
+                );
+                controls_left = track_icon_rect.left_bottom();
+                let track_menu_id = playback_response.id.with("track_menu");
+                let mut track_menu_open: bool = ui
+                    .ctx()
+                    .memory_mut(|m| *m.data.get_temp_mut_or_default(track_menu_id));
+                if ui
+                    .interact(
+                        track_icon_rect,
+                        playback_response.id.with("track_icon_sense"),
+                        Sense::click(),
+                    )
+                    .clicked()
+                {
+                    track_menu_open = !track_menu_open;
+                }
+                if track_menu_open {
+                    let current_video_track = self.video_track();
+                    let current_audio_track = self.audio_track();
+                    let current_subtitle_track = self.subtitle_track();
+                    // (track type, track or `None` for no subtitles, is selected)
+                    let mut track_options = self
+                        .video_tracks
+                        .iter()
+                        .map(|t| {
+                            let selected = Some(t.stream_index) == current_video_track;
+                            (Type::Video, Some(t.clone()), selected)
+                        })
+                        .chain(self.audio_tracks.iter().map(|t| {
+                            let selected = Some(t.stream_index) == current_audio_track;
+                            (Type::Audio, Some(t.clone()), selected)
+                        }))
+                        .chain(self.subtitle_tracks.iter().map(|t| {
+                            let selected = Some(t.stream_index) == current_subtitle_track;
+                            (Type::Subtitle, Some(t.clone()), selected)
+                        }))
+                        .collect::<Vec<_>>();
+                    if !self.subtitle_tracks.is_empty() {
+                        track_options.push((
+                            Type::Subtitle,
+                            None,
+                            self.subtitle_streamer.is_none(),
+                        ));
+                    }
+                    // the subtitle offset can be adjusted in a row below the tracks
+                    let track_menu_rows =
+                        track_options.len() + self.subtitle_streamer.is_some() as usize;
+                    let track_option_height = 18.;
+                    let track_option_width = 180.;
+                    let track_menu_margin = 5.;
+                    let track_menu_rect = Rect::from_min_size(
+                        track_icon_rect.right_top()
+                            - vec2(
+                                track_option_width,
+                                track_menu_margin + track_option_height * track_menu_rows as f32,
+                            ),
+                        vec2(
+                            track_option_width,
+                            track_option_height * track_menu_rows as f32,
+                        ),
+                    );
+                    ui.painter().rect_filled(
+                        track_menu_rect,
+                        Rounding::same(5.),
+                        Color32::from_black_alpha(150).linear_multiply(seekbar_anim_frac),
+                    );
+                    for (i, (track_type, track, selected)) in track_options.into_iter().enumerate()
+                    {
+                        let track_option_rect = Rect::from_min_size(
+                            track_menu_rect.left_top() + vec2(0., i as f32 * track_option_height),
+                            vec2(track_option_width, track_option_height),
+                        );
+                        let track_option_color = if selected {
+                            text_color
+                        } else {
+                            Color32::GRAY.linear_multiply(seekbar_anim_frac)
+                        };
+                        let track_icon = match track_type {
+                            Type::Video => "🎞",
+                            Type::Audio => "🔊",
+                            _ => "💬",
+                        };
+                        let track_label = track
+                            .as_ref()
+                            .map(TrackInfo::label)
+                            .unwrap_or_else(|| "off".to_string());
+                        ui.painter().text(
+                            track_option_rect.left_center() + vec2(track_menu_margin, 0.),
+                            Align2::LEFT_CENTER,
+                            format!("{track_icon} {track_label}"),
+                            speed_text_font_id.clone(),
+                            track_option_color,
+                        );
+                        if ui
+                            .interact(track_option_rect, track_menu_id.with(i), Sense::click())
+                            .clicked()
+                        {
+                            let selected_track = match (track_type, track) {
+                                (Type::Video, Some(track)) => {
+                                    self.select_video_track(track.stream_index)
+                                }
+                                (Type::Audio, Some(track)) => {
+                                    self.select_audio_track(track.stream_index)
+                                }
+                                (_, Some(track)) => self.select_subtitle_track(track.stream_index),
+                                (_, None) => {
+                                    self.clear_subtitles();
+                                    Ok(())
+                                }
+                            };
+                            if let Err(e) = selected_track {
+                                self.events.emit_error(e);
+                            }
+                            track_menu_open = false;
+                        }
+                    }
+                    if self.subtitle_streamer.is_some() {
+                        let offset_row_rect = Rect::from_min_size(
+                            track_menu_rect.left_bottom() - vec2(0., track_option_height),
+                            vec2(track_option_width, track_option_height),
+                        );
+                        ui.painter().text(
+                            offset_row_rect.center(),
+                            Align2::CENTER_CENTER,
+                            format!("⏴ 💬 {:+.1}s ⏵", self.subtitle_offset_ms as f32 / 1000.),
+                            speed_text_font_id.clone(),
+                            text_color,
+                        );
+                        let mut earlier_rect = offset_row_rect;
+                        earlier_rect.set_right(offset_row_rect.center().x);
+                        let mut later_rect = offset_row_rect;
+                        later_rect.set_left(offset_row_rect.center().x);
+                        for (offset_rect, offset_change_ms, id) in [
+                            (earlier_rect, -SUBTITLE_OFFSET_STEP_MS, "subtitle_earlier"),
+                            (later_rect, SUBTITLE_OFFSET_STEP_MS, "subtitle_later"),
+                        ] {
+                            if ui
+                                .interact(offset_rect, track_menu_id.with(id), Sense::click())
+                                .clicked()
+                            {
+                                self.subtitle_offset_ms += offset_change_ms;
+                            }
+                        }
+                    }
+                }
+                ui.ctx()
+                    .memory_mut(|m| m.data.insert_temp(track_menu_id, track_menu_open));
+            }
+
+            if self.subtitle_streamer.is_some() {
+                let subtitle_icon_color = if self.subtitles_visible {
+                    text_color
+                } else {
+                    Color32::GRAY.linear_multiply(seekbar_anim_frac)
+                };
+                let subtitle_icon_rect = ui.painter().text(
+                    controls_left + vec2(-10., 0.),
+                    Align2::RIGHT_BOTTOM,
+                    "💬",
+                    speed_text_font_id.clone(),
+                    subtitle_icon_color,
+                );
+                if ui
+                    .interact(
+                        subtitle_icon_rect,
+                        playback_response.id.with("subtitle_icon_sense"),
+                        Sense::click(),
+                    )
+                    .clicked()
+                {
+                    self.subtitles_visible = !self.subtitles_visible;
+                }
+            }
+
             if self.audio_streamer.is_some() {
                 let sound_icon_rect = ui.painter().text(
                     sound_icon_pos,
@@ -555,57 +2404,96 @@ impl Player {
         }
     }
 
-    #[cfg(feature = "from_bytes")]
-    /// Create a new [`Player`] from input bytes.
+    /// Create a new [`Player`] from input bytes. The bytes are read in place, nothing is written to disk.
     pub fn new_from_bytes(
         ctx: &egui::Context,
-        input_bytes: &[u8],
+        input_bytes: impl Into<Arc<[u8]>>,
         config: PlayerConfig,
     ) -> Result<Self> {
-        let mut file = tempfile::Builder::new().tempfile()?;
-        file.write_all(input_bytes)?;
-        let path = file.path();
-        let mut slf = Self::new(ctx, path, config)?;
-        slf.temp_file = Some(file);
-        Ok(slf)
+        let input_bytes: Arc<[u8]> = input_bytes.into();
+        Self::from_source(ctx, input_bytes, config)
     }
 
-    /// Initializes the audio stream (if there is one), required for making a [`Player`] output audio.
-    pub fn with_audio(mut self, audio_device: &mut AudioDevice) -> Result<Self> {
-        let audio_input_context = input(&self.input_path)?;
+    /// Create a new [`Player`] from a reader. The reader is shared between the video and audio streams.
+    pub fn new_from_reader(
+        ctx: &egui::Context,
+        reader: impl ReadSeek + 'static,
+        config: PlayerConfig,
+    ) -> Result<Self> {
+        Self::from_source(ctx, ReaderSource::new(reader), config)
+    }
+
+    /// Initializes the audio stream (if there is one), required for making a [`Player`] output audio. The
+    /// audio plays through any [`AudioBackend`], such as the [`AudioDevice`] from [`init_audio_device`].
+    pub fn with_audio(mut self, audio_backend: &mut (impl AudioBackend + ?Sized)) -> Result<Self> {
+        let audio_input_context = self.media_source.open()?;
         let audio_stream = audio_input_context.streams().best(Type::Audio);
+        self.audio_stream_handle = None;
+        self.audio_track = audio_stream.as_ref().map(|stream| stream.index());
+        // requests for the audio streamer being replaced
+        self.audio_requests.lock().clear();
 
         let audio_streamer = if let Some(audio_stream) = audio_stream.as_ref() {
             let audio_stream_index = audio_stream.index();
+            let time_base = audio_stream.time_base();
+            let start_time = stream_start_time(audio_stream);
             let audio_context =
-                ffmpeg::codec::context::Context::from_parameters(audio_stream.parameters())?;
-            let audio_decoder = audio_context.decoder().audio()?;
-            let audio_sample_buffer =
-                SharedRb::<f32, Vec<_>>::new(audio_device.spec().size as usize);
+                ffmpeg::codec::context::Context::from_parameters(audio_stream.parameters())
+                    .map_err(Error::Probe)?;
+            let audio_decoder = audio_context.decoder().audio().map_err(|e| {
+                Error::UnsupportedFormat(format!("failed to open audio decoder: {e}"))
+            })?;
+            let audio_spec = audio_backend.spec();
+            let min_buffer_size = audio_spec.sample_rate as usize
+                * audio_spec.channels as usize
+                * AUDIO_SAMPLE_BUFFER_MIN_MS
+                / 1000;
+            let audio_sample_buffer = SharedRb::<f32, Vec<_>>::new(
+                (audio_spec.buffer_size * AUDIO_SAMPLE_BUFFER_PULLS).max(min_buffer_size),
+            );
             let (audio_sample_producer, audio_sample_consumer) = audio_sample_buffer.split();
-            let audio_resampler = software::resampling::context::Context::get(
-                audio_decoder.format(),
-                audio_decoder.channel_layout(),
-                audio_decoder.rate(),
-                audio_device.spec().format.to_sample(),
-                ChannelLayout::STEREO,
-                audio_device.spec().freq as u32,
+            let mut audio_resampler = audio_resampler(
+                &audio_decoder,
+                audio_spec.channel_layout(),
+                audio_spec.sample_rate,
             )?;
+            if self.channel_mixing != ChannelMixing::default() {
+                audio::apply_channel_mixing(&mut audio_resampler, &self.channel_mixing)?;
+            }
 
-            audio_device.lock().sample_streams.push(AudioSampleStream {
-                sample_consumer: audio_sample_consumer,
-                audio_volume: self.audio_volume.clone(),
-            });
+            let stream_handle = Arc::new(audio_backend.mixer().add_stream(
+                audio_sample_consumer,
+                self.audio_volume.clone(),
+                self.events.clone(),
+            ));
+            self.audio_stream_handle = Some(Arc::clone(&stream_handle));
+            stream_handle.set_playing(is_audible_state(self.player_state.get()));
 
-            audio_device.resume();
+            audio_backend.resume()?;
             Some(AudioStreamer {
                 player_state: self.player_state.clone(),
-                _video_elapsed_ms: self.video_elapsed_ms.clone(),
-                audio_elapsed_ms: self.audio_elapsed_ms.clone(),
+                clock: self.clock.clone(),
+                audio_elapsed_ms: Cache::new(0),
+                read_until_ms: self.audio_read_until_ms.clone(),
+                ended: self.audio_ended.clone(),
+                audio_clock_ms: self.audio_elapsed_ms.clone(),
+                queued_until_ms: 0,
+                output_rate: audio_spec.sample_rate,
+                output_channels: audio_spec.channels as usize,
+                sync_mode: self.sync_mode(),
+                sync_tolerance_ms: self.config.sync_tolerance_ms,
+                playback_speed: self.playback_speed.clone(),
+                tempo_filter: None,
+                channel_mixing: self.channel_mixing.clone(),
                 audio_sample_producer,
+                pending_samples: vec![],
+                pending_start_ms: None,
+                stream_handle,
                 input_context: audio_input_context,
                 audio_decoder,
                 audio_stream_index,
+                time_base,
+                start_time,
                 resampler: audio_resampler,
             })
         } else {
@@ -615,85 +2503,223 @@ impl Player {
         Ok(self)
     }
 
-    /// Create a new [`Player`].
+    /// Create a new [`Player`] on a background thread. Poll the returned [`PlayerLoader`] until the player is
+    /// ready, showing [`PlayerLoader::ui`] in its place until then. Audio can be added with
+    /// [`Player::with_audio`] once the player is ready.
+    pub fn open_async(
+        ctx: &egui::Context,
+        input_path: impl AsRef<Path>,
+        config: PlayerConfig,
+    ) -> PlayerLoader {
+        Self::open_source_async(ctx, input_path.as_ref().to_path_buf(), config)
+    }
+
+    /// Like [`Player::open_async`], but for any [`MediaSource`].
+    pub fn open_source_async(
+        ctx: &egui::Context,
+        media_source: impl MediaSource + 'static,
+        config: PlayerConfig,
+    ) -> PlayerLoader {
+        let (player_sender, player_receiver) = mpsc::channel();
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let media_source = InterruptibleSource {
+            source: media_source,
+            interrupt: Arc::clone(&interrupt),
+        };
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = player_sender.send(Self::from_source(&ctx, media_source, config));
+            ctx.request_repaint();
+        });
+        PlayerLoader {
+            player_receiver,
+            interrupt,
+            started_at: Instant::now(),
+            timeout: DEFAULT_LOAD_TIMEOUT,
+            error: None,
+            finished: false,
+        }
+    }
+
+    /// Create a new [`Player`]. Blocks until the first frame has been decoded, see [`Player::open_async`]
+    /// for a non-blocking alternative.
     pub fn new(
         ctx: &egui::Context,
         input_path: impl AsRef<Path>,
         config: PlayerConfig,
     ) -> Result<Self> {
-        let input_context = input(&input_path)?;
+        Self::from_source(ctx, input_path.as_ref().to_path_buf(), config)
+    }
+
+    /// Create a new [`Player`] from any [`MediaSource`]. Both the video and the audio stream are opened from it.
+    pub fn from_source(
+        ctx: &egui::Context,
+        media_source: impl MediaSource + 'static,
+        config: PlayerConfig,
+    ) -> Result<Self> {
+        let media_source: Arc<dyn MediaSource> = Arc::new(media_source);
+        let input_context = media_source.open()?;
+        let media_info = MediaInfo::from_input(&input_context)?;
+        // cover art is stored as a video stream with a single picture
+        let is_cover_art = |stream: &ffmpeg::Stream| {
+            stream
+                .disposition()
+                .contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+        };
+        let tracks_of_type = |medium: Type| {
+            input_context
+                .streams()
+                .filter(|stream| stream.parameters().medium() == medium && !is_cover_art(stream))
+                .map(|stream| TrackInfo::from_stream(&stream))
+                .collect::<Vec<_>>()
+        };
+        let video_tracks = tracks_of_type(Type::Video);
+        let video_track_sizes = input_context
+            .streams()
+            .filter(|stream| stream.parameters().medium() == Type::Video && !is_cover_art(stream))
+            .map(|stream| video_stream_size(&stream))
+            .collect();
+        let audio_tracks = tracks_of_type(Type::Audio);
+        let subtitle_tracks = tracks_of_type(Type::Subtitle);
         let video_stream = input_context
             .streams()
             .best(Type::Video)
-            .ok_or(ffmpeg::Error::StreamNotFound)?;
-        let video_stream_index = video_stream.index();
+            .filter(|stream| !is_cover_art(stream))
+            .or_else(|| {
+                let first_video_track = video_tracks.first()?;
+                input_context.stream(first_video_track.stream_index)
+            });
+        if video_stream.is_none() && audio_tracks.is_empty() {
+            return Err(Error::Probe(ffmpeg::Error::StreamNotFound));
+        }
+        let cover_art_stream_index = input_context
+            .streams()
+            .find(|stream| is_cover_art(stream))
+            .map(|stream| stream.index());
         let max_audio_volume = 1.;
 
         let audio_volume = Cache::new(max_audio_volume / 2.);
 
         let video_elapsed_ms = Cache::new(0);
-        let audio_elapsed_ms = Cache::new(0);
+        let audio_elapsed_ms = Cache::new(None);
+        let video_read_until_ms = Cache::new(0);
+        let video_ended = Cache::new(false);
+        let frame_queue = FrameQueue::default();
         let player_state = Cache::new(PlayerState::Stopped);
 
-        let video_context =
-            ffmpeg::codec::context::Context::from_parameters(video_stream.parameters())?;
-        let video_decoder = video_context.decoder().video()?;
-        let framerate = (video_stream.avg_frame_rate().numerator() as f64)
-            / video_stream.avg_frame_rate().denominator() as f64;
-
-        let (width, height) = (video_decoder.width(), video_decoder.height());
-        let frame_scaler = software::scaling::Context::get(
-            video_decoder.format(),
-            video_decoder.width(),
-            video_decoder.height(),
-            Pixel::RGB24,
-            video_decoder.width(),
-            video_decoder.height(),
-            software::scaling::flag::Flags::BILINEAR,
-        )?;
-
-        let duration_ms = timestamp_to_millisec(input_context.duration(), AV_TIME_BASE_RATIONAL); // in sec
-        let stream_decoder = VideoStreamer {
-            video_decoder,
-            video_stream_index,
-            _audio_elapsed_ms: audio_elapsed_ms.clone(),
-            video_elapsed_ms: video_elapsed_ms.clone(),
-            input_context,
-            player_state: player_state.clone(),
-            scaler: frame_scaler,
+        let framerate = if let Some(video_stream) = video_stream.as_ref() {
+            // variable frame rate streams (such as gifs) may not report an average frame rate
+            let frame_rate = if video_stream.avg_frame_rate().numerator() > 0 {
+                video_stream.avg_frame_rate()
+            } else {
+                video_stream.rate()
+            };
+            frame_rate.numerator() as f64 / frame_rate.denominator().max(1) as f64
+        } else {
+            AUDIO_ONLY_FRAME_RATE
         };
+        let video_stream_index = video_stream.map(|stream| stream.index());
+
+        // live streams have no duration
+        let duration_ms =
+            timestamp_to_millisec(input_context.duration().max(0), AV_TIME_BASE_RATIONAL);
         let texture_options = TextureOptions::LINEAR;
-        let texture_handle = ctx.load_texture("vidstream", ColorImage::example(), texture_options);
+        let mut texture_handle =
+            ctx.load_texture("vidstream", ColorImage::example(), texture_options);
+        let mut cover_art = None;
+        let mut visualizer = None;
+        let (video_streamer, [width, height]) = if let Some(stream_index) = video_stream_index {
+            let stream_decoder = VideoStreamer::new(
+                input_context,
+                stream_index,
+                player_state.clone(),
+                Arc::clone(&frame_queue),
+                video_read_until_ms.clone(),
+                video_ended.clone(),
+            )?;
+            let (width, height) = stream_decoder.native_size();
+            (Some(Arc::new(Mutex::new(stream_decoder))), [width, height])
+        } else {
+            // audio-only media shows its cover art in place of the video, or a visualizer without one
+            cover_art = cover_art_stream_index.and_then(|stream_index| {
+                placeholder::decode_cover_art(media_source.as_ref(), stream_index).ok()
+            });
+            let size = if let Some(cover_art) = cover_art.clone() {
+                let size = [cover_art.size[0] as u32, cover_art.size[1] as u32];
+                texture_handle = ctx.load_texture("vidstream", cover_art, texture_options);
+                size
+            } else {
+                visualizer = Some(Visualizer::default());
+                AUDIO_ONLY_VISUALIZER_SIZE
+            };
+            (None, size)
+        };
         let mut streamer = Self {
-            input_path: input_path.as_ref().to_path_buf(),
+            media_source,
+            media_info,
+            video_tracks,
+            video_track_sizes,
+            audio_tracks,
+            subtitle_tracks,
+            subtitle_streamer: None,
+            subtitle_textures: SubtitleTextures::default(),
+            subtitle_offset_ms: 0,
+            subtitles_visible: true,
+            thumbnails: None,
+            cover_art,
+            shown_frame_ms: None,
+            snapshot_extractor: None,
+            channel_mixing: ChannelMixing::default(),
+            visualizer,
             audio_streamer: None,
-            video_streamer: Arc::new(Mutex::new(stream_decoder)),
+            video_streamer,
             texture_options,
             framerate,
-            frame_timer: Timer::new(),
-            audio_timer: Timer::new(),
             preseek_player_state: None,
-            frame_thread: None,
+            decode_thread: None,
             audio_thread: None,
+            audio_stream_handle: None,
+            frame_queue,
+            video_requests: VideoRequests::default(),
+            audio_requests: AudioRequests::default(),
+            video_track: video_stream_index,
+            audio_track: None,
+            pending_steps: 0,
+            clock: PlaybackClock::new(),
+            external_clock: None,
+            events: EventSenders::default(),
+            reported_player_state: PlayerState::Stopped,
+            first_frame_pending: true,
+            buffer_underrun: false,
             texture_handle,
             player_state,
             video_elapsed_ms,
             audio_elapsed_ms,
+            video_read_until_ms,
+            audio_read_until_ms: Cache::new(0),
+            video_ended,
+            audio_ended: Cache::new(false),
             width,
             last_seek_ms: None,
             duration_ms,
             audio_volume,
             max_audio_volume,
+            playback_speed: Cache::new(1.),
+            seek_mode: Cache::new(config.seek_mode),
             config,
             height,
             ctx_ref: ctx.clone(),
-            #[cfg(feature = "from_bytes")]
-            temp_file: None,
         };
 
-        loop {
-            if let Ok(_texture_handle) = streamer.try_set_texture_handle() {
-                break;
+        let mut attempts = 0;
+        while streamer.video_streamer.is_some() {
+            attempts += 1;
+            match streamer.try_set_texture_handle() {
+                Ok(_texture_handle) => break,
+                // the stream ended without a single frame being decoded
+                Err(e) if e.is_eof() => return Err(Error::Probe(ffmpeg::Error::InvalidData)),
+                Err(e) if attempts >= FIRST_FRAME_ATTEMPTS => return Err(e),
+                Err(_) => (),
             }
         }
 
@@ -701,12 +2727,11 @@ impl Player {
     }
 
     fn try_set_texture_handle(&mut self) -> Result<TextureHandle> {
-        match self
+        let video_streamer = self
             .video_streamer
-            .lock()
-            .recieve_next_packet_until_frame()
-            // .and_then(|mut s| Ok(s.))
-        {
+            .as_ref()
+            .ok_or_else(|| Error::Unavailable("the media has no video".to_string()))?;
+        match video_streamer.lock().recieve_next_packet_until_frame() {
             Ok(first_frame) => {
                 let texture_handle =
                     self.ctx_ref
@@ -726,87 +2751,188 @@ pub trait Streamer {
     type Frame;
     /// The associated type after the frame is processed.
     type ProcessedFrame;
-    /// Process the streamer's state.
+    /// Process the streamer's state. Reaching the end of the stream is not an error.
     fn process_state(
         &mut self,
         duration_ms: i64,
+        seek_mode: SeekMode,
         seek_preview: bool,
         apply_processed_frame: impl FnOnce(Self::ProcessedFrame),
-    ) {
+    ) -> Result<()> {
         let player_state = self.player_state().get();
-        if player_state == PlayerState::Playing {
+        // the video is decoded ahead of the audio, so the end of file may be reached while audio is still left
+        if matches!(player_state, PlayerState::Playing | PlayerState::EndOfFile) {
             match self.recieve_next_packet_until_frame() {
                 Ok(frame) => {
                     apply_processed_frame(frame);
                 }
-                Err(_e) => {}
+                Err(e) if !e.is_eof() => return Err(e),
+                Err(_) => {}
             }
         } else if let PlayerState::Seeking(seek_frac) = player_state {
-            let target_ms = (seek_frac as f64 * duration_ms as f64) as i64;
-            let seeking_forward = target_ms > self.elapsed_ms().get();
-            let target_ts = millisec_to_timestamp(target_ms, rescale::TIME_BASE);
-            if let Err(e) = self.input_context().seek(target_ts, ..target_ts) {
-                dbg!(e);
-            } else if seek_frac >= 0.99 {
-                // prevent inifinite loop near end of stream
-                self.player_state().set(PlayerState::EndOfFile)
-            } else if seek_frac > 0. {
-                // this drop frame loop lets us refresh until current_ts is accurate
-                if !seeking_forward {
-                    while (self.elapsed_ms().get() as f64 / duration_ms as f64) > seek_frac as f64 {
-                        self.drop_frames();
-                    }
-                }
+            self.seek(
+                seek_frac,
+                duration_ms,
+                seek_mode,
+                seek_preview,
+                apply_processed_frame,
+            )?;
+        }
+        Ok(())
+    }
 
-                // this drop frame loop drops frames until we are at desired
-                while (self.elapsed_ms().get() as f64 / duration_ms as f64) < seek_frac as f64 {
-                    self.drop_frames();
-                }
+    /// Seek the stream to `seek_frac` (from `0` to `1`) of `duration_ms`. If `seek_preview` is set, the
+    /// frame at the seek location is processed and passed to `apply_processed_frame`.
+    fn seek(
+        &mut self,
+        seek_frac: f32,
+        duration_ms: i64,
+        seek_mode: SeekMode,
+        seek_preview: bool,
+        apply_processed_frame: impl FnOnce(Self::ProcessedFrame),
+    ) -> Result<()> {
+        let target_ms = (seek_frac as f64 * duration_ms as f64) as i64;
+        self.seek_ms(target_ms, seek_mode, seek_preview, apply_processed_frame)
+    }
 
-                // frame preview
-                if seek_preview {
-                    if let Ok(frame) = self.recieve_next_packet_until_frame() {
-                        apply_processed_frame(frame)
+    /// Seek the stream to `target_ms`. An accurate seek stops at the first frame at or after `target_ms`, so
+    /// seeking to the timestamp of a frame lands on that frame. See [`Streamer::seek`].
+    fn seek_ms(
+        &mut self,
+        target_ms: i64,
+        seek_mode: SeekMode,
+        seek_preview: bool,
+        apply_processed_frame: impl FnOnce(Self::ProcessedFrame),
+    ) -> Result<()> {
+        let frame = if seek_mode == SeekMode::Keyframe {
+            self.seek_to_nearest_keyframe(target_ms)?
+        } else {
+            self.seek_to_keyframe_before(target_ms)?;
+            // frames between the keyframe and the target are decoded but not processed. past the last frame,
+            // the last frame is shown
+            let mut last_frame = None;
+            loop {
+                match self.decode_frame() {
+                    Ok(frame) => {
+                        let reached_target = self.elapsed_ms().get() >= target_ms;
+                        last_frame = Some(frame);
+                        if reached_target {
+                            break;
+                        }
                     }
+                    Err(e) if e.is_eof() => break,
+                    Err(_) => self.recieve_next_packet()?,
                 }
-            };
+            }
+            last_frame
+        };
+        if let (true, Some(frame)) = (seek_preview, frame) {
+            apply_processed_frame(self.process_frame(frame)?);
+        }
+        Ok(())
+    }
+    /// Seek to whichever keyframe is closest to `target_ms`, returning the first frame decoded there.
+    fn seek_to_nearest_keyframe(&mut self, target_ms: i64) -> Result<Option<Self::Frame>> {
+        let start_ms = timestamp_to_millisec(self.start_time(), self.time_base());
+        let target_ts = millisec_to_timestamp(target_ms.max(0) + start_ms, rescale::TIME_BASE);
+        // there is no keyframe after the target if the forward seek fails
+        let after_ms = match self.input_context().seek(target_ts, target_ts..) {
+            Ok(()) => {
+                self.decoder().flush();
+                self.ended().set(false);
+                self.decode_first_frame()?.map(|_| self.elapsed_ms().get())
+            }
+            Err(_) => None,
+        };
+        self.seek_to_keyframe_before(target_ms)?;
+        let before = self.decode_first_frame()?;
+        let before_ms = self.elapsed_ms().get();
+        match (before, after_ms) {
+            (Some(before), Some(after_ms)) if after_ms - target_ms >= target_ms - before_ms => {
+                Ok(Some(before))
+            }
+            (before, None) => Ok(before),
+            (_, Some(_)) => {
+                self.input_context()
+                    .seek(target_ts, target_ts..)
+                    .map_err(Error::Seek)?;
+                self.decoder().flush();
+                self.ended().set(false);
+                self.decode_first_frame()
+            }
+        }
+    }
+    /// Decode the first frame after a seek, or `None` if the end of the stream comes first.
+    fn decode_first_frame(&mut self) -> Result<Option<Self::Frame>> {
+        loop {
+            match self.decode_frame() {
+                Ok(frame) => return Ok(Some(frame)),
+                Err(e) if e.is_eof() => return Ok(None),
+                Err(_) => self.recieve_next_packet()?,
+            }
         }
     }
+    /// Seek to the keyframe at or before `target_ms`, discarding anything left in the decoder.
+    fn seek_to_keyframe_before(&mut self, target_ms: i64) -> Result<()> {
+        let start_ms = timestamp_to_millisec(self.start_time(), self.time_base());
+        let target_ts = millisec_to_timestamp(target_ms.max(0) + start_ms, rescale::TIME_BASE);
+        self.input_context()
+            .seek(target_ts, ..target_ts)
+            .map_err(Error::Seek)?;
+        self.decoder().flush();
+        self.ended().set(false);
+        Ok(())
+    }
 
     /// The stream index.
     fn stream_index(&self) -> usize;
     /// The elapsed time, in milliseconds.
     fn elapsed_ms(&mut self) -> &mut Cache<i64>;
+    /// The time base of the stream.
+    fn time_base(&self) -> Rational;
+    /// The timestamp the stream starts at, in the stream's time base.
+    fn start_time(&self) -> i64;
     /// The streamer's decoder.
     fn decoder(&mut self) -> &mut ffmpeg::decoder::Opened;
     /// The streamer's input context.
     fn input_context(&mut self) -> &mut Input;
     /// The streamer's state.
     fn player_state(&mut self) -> &mut Cache<PlayerState>;
+    /// How far the demuxer has read the stream, in milliseconds.
+    fn read_until_ms(&mut self) -> &mut Cache<i64>;
+    /// Whether the decoder has output the last frame of the stream, until the stream is seeked.
+    fn ended(&mut self) -> &mut Cache<bool>;
 
     /// Output a frame from the decoder.
     fn decode_frame(&mut self) -> Result<Self::Frame>;
-    /// Ignore the remainder of this packet.
-    fn drop_frames(&mut self) {
-        if self.decode_frame().is_err() {
-            let _ = self.recieve_next_packet();
-        } else {
-            self.drop_frames();
+    /// Update the elapsed time from the presentation timestamp of a decoded frame.
+    fn set_elapsed_from_frame(&mut self, frame: &ffmpeg::Frame) {
+        if let Some(timestamp) = frame.timestamp().or_else(|| frame.pts()) {
+            let elapsed_ms = timestamp_to_millisec(timestamp - self.start_time(), self.time_base());
+            self.elapsed_ms().set(elapsed_ms);
         }
     }
     /// Recieve the next packet of the stream.
     fn recieve_next_packet(&mut self) -> Result<()> {
-        if let Some((stream, packet)) = self.input_context().packets().next() {
-            let time_base = stream.time_base();
-            if stream.index() == self.stream_index() {
-                self.decoder().send_packet(&packet)?;
-                if let Some(dts) = packet.dts() {
-                    self.elapsed_ms().set(timestamp_to_millisec(dts, time_base));
+        let mut packet = Packet::empty();
+        match packet.read(self.input_context()) {
+            Ok(()) => {
+                if packet.stream() == self.stream_index() {
+                    if let Some(timestamp) = packet.pts().or_else(|| packet.dts()) {
+                        let read_until_ms = timestamp_to_millisec(
+                            timestamp + packet.duration() - self.start_time(),
+                            self.time_base(),
+                        );
+                        self.read_until_ms().set(read_until_ms);
+                    }
+                    self.decoder().send_packet(&packet).map_err(Error::Decode)?;
                 }
             }
-        } else {
-            self.decoder().send_eof()?;
-            self.player_state().set(PlayerState::EndOfFile);
+            // the decoder is drained of the frames it holds back, and ends the stream once it's empty
+            Err(ffmpeg::Error::Eof) => self.decoder().send_eof().map_err(Error::Decode)?,
+            // the input was interrupted, such as by a `PlayerLoader` that timed out
+            Err(ffmpeg::Error::Exit) => return Err(Error::Timeout),
+            Err(e) => return Err(Error::Decode(e)),
         }
         Ok(())
     }
@@ -816,6 +2942,7 @@ pub trait Streamer {
         let beginning_seek = beginning.rescale((1, 1), rescale::TIME_BASE);
         let _ = self.input_context().seek(beginning_seek, ..beginning_seek);
         self.decoder().flush();
+        self.ended().set(false);
 
         if start_playing {
             self.player_state().set(PlayerState::Playing);
@@ -826,11 +2953,11 @@ pub trait Streamer {
         match self.recieve_next_frame() {
             Ok(frame_result) => Ok(frame_result),
             Err(e) => {
-                if matches!(e.downcast_ref::<ffmpeg::Error>(), Some(ffmpeg::Error::Eof)) {
-                    Err(e)
-                } else {
+                if e.needs_packet() {
                     self.recieve_next_packet()?;
                     self.recieve_next_packet_until_frame()
+                } else {
+                    Err(e)
                 }
             }
         }
@@ -841,8 +2968,204 @@ pub trait Streamer {
     fn recieve_next_frame(&mut self) -> Result<Self::ProcessedFrame> {
         match self.decode_frame() {
             Ok(decoded_frame) => self.process_frame(decoded_frame),
-            Err(e) => Err(e),
+            Err(e) => {
+                if e.is_eof() {
+                    self.ended().set(true);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+impl VideoStreamer {
+    /// Create a new [`VideoStreamer`] decoding the video stream `stream_index` of the input, into frames of
+    /// the video's native size.
+    fn new(
+        input_context: MediaInput,
+        stream_index: usize,
+        player_state: Cache<PlayerState>,
+        frame_queue: FrameQueue,
+        read_until_ms: Cache<i64>,
+        ended: Cache<bool>,
+    ) -> Result<Self> {
+        let (video_decoder, time_base, start_time) =
+            open_video_decoder(&input_context, stream_index)?;
+        let scaler = rgb_scaler(
+            &video_decoder,
+            video_decoder.width(),
+            video_decoder.height(),
+        )?;
+        Ok(Self {
+            video_decoder,
+            video_stream_index: stream_index,
+            video_elapsed_ms: Cache::new(0),
+            read_until_ms,
+            ended,
+            input_context,
+            player_state,
+            scaler,
+            frame_queue,
+            time_base,
+            start_time,
+            requested_position_ms: 0,
+        })
+    }
+    /// The native size of the video's frames.
+    fn native_size(&self) -> (u32, u32) {
+        (self.video_decoder.width(), self.video_decoder.height())
+    }
+    /// Scale frames to the given size from now on, if they aren't already.
+    fn set_output_size(&mut self, width: u32, height: u32) -> Result<()> {
+        let output = self.scaler.output();
+        if (output.width, output.height) != (width, height) {
+            self.scaler = rgb_scaler(&self.video_decoder, width, height)?;
+        }
+        Ok(())
+    }
+    /// Decode another video stream of the input from now on.
+    fn select_stream(&mut self, stream_index: usize) -> Result<()> {
+        let (video_decoder, time_base, start_time) =
+            open_video_decoder(&self.input_context, stream_index)?;
+        self.scaler = rgb_scaler(
+            &video_decoder,
+            video_decoder.width(),
+            video_decoder.height(),
+        )?;
+        self.video_decoder = video_decoder;
+        self.video_stream_index = stream_index;
+        self.time_base = time_base;
+        self.start_time = start_time;
+        self.frame_queue.lock().clear();
+        Ok(())
+    }
+    /// Replace the frame queue with the frame before the one at `current_ms` (to be presented immediately),
+    /// followed by the frame at `current_ms`. Returns the timestamp of the previous frame.
+    fn queue_previous_frame(&mut self, current_ms: i64) -> Result<i64> {
+        self.frame_queue.lock().clear();
+        let mut seek_ms = current_ms - 1;
+        loop {
+            self.seek_to_keyframe_before(seek_ms)?;
+            let mut previous_frame = None;
+            let mut current_frame = None;
+            // decode forward from the keyframe, only keeping the frames on either side of `current_ms`
+            loop {
+                match self.decode_frame() {
+                    Ok(frame) => {
+                        let timestamp_ms = self.video_elapsed_ms.get();
+                        if timestamp_ms >= current_ms {
+                            current_frame = Some((frame, timestamp_ms));
+                            break;
+                        }
+                        previous_frame = Some((frame, timestamp_ms));
+                    }
+                    Err(e) => {
+                        if e.is_eof() {
+                            break;
+                        }
+                        self.recieve_next_packet()?;
+                    }
+                }
+            }
+
+            if previous_frame.is_none() && seek_ms > 0 {
+                // the frame at `current_ms` is a keyframe, so look further back for the previous one
+                seek_ms -= STEP_BACKWARD_SEEK_MS;
+                continue;
+            }
+
+            let mut shown_frame_ms = current_ms;
+            for (i, (frame, timestamp_ms)) in
+                previous_frame.into_iter().chain(current_frame).enumerate()
+            {
+                let image = self.process_frame(frame)?;
+                if i == 0 {
+                    shown_frame_ms = timestamp_ms;
+                }
+                self.frame_queue.lock().push_back(QueuedFrame {
+                    image,
+                    timestamp_ms,
+                    present_immediately: i == 0,
+                });
+            }
+            return Ok(shown_frame_ms);
+        }
+    }
+    /// Decode the next frame and append it to the frame queue.
+    fn decode_into_queue(&mut self) -> Result<()> {
+        let image = self.recieve_next_packet_until_frame()?;
+        let timestamp_ms = self.video_elapsed_ms.get();
+        self.frame_queue.lock().push_back(QueuedFrame {
+            image,
+            timestamp_ms,
+            present_immediately: false,
+        });
+        Ok(())
+    }
+    /// Seek the stream, replacing the contents of the frame queue with a preview of the seek location.
+    fn seek_into_queue(
+        &mut self,
+        seek_frac: f32,
+        duration_ms: i64,
+        seek_mode: SeekMode,
+    ) -> Result<()> {
+        let mut preview_image = None;
+        self.frame_queue.lock().clear();
+        self.seek(seek_frac, duration_ms, seek_mode, true, |image| {
+            preview_image = Some(image)
+        })?;
+        if let Some(image) = preview_image {
+            let timestamp_ms = self.video_elapsed_ms.get();
+            self.requested_position_ms = timestamp_ms;
+            self.frame_queue.lock().push_back(QueuedFrame {
+                image,
+                timestamp_ms,
+                present_immediately: true,
+            });
+        }
+        Ok(())
+    }
+    /// Carry out a request from the ui, on the decode thread.
+    fn handle_request(
+        &mut self,
+        video_request: VideoRequest,
+        duration_ms: i64,
+        audio_requests: Option<&AudioRequests>,
+    ) -> Result<()> {
+        match video_request {
+            VideoRequest::Reset => {
+                self.reset(false);
+                self.frame_queue.lock().clear();
+                self.requested_position_ms = 0;
+            }
+            VideoRequest::Seek {
+                seek_frac,
+                seek_mode,
+            } => self.seek_into_queue(seek_frac, duration_ms, seek_mode)?,
+            VideoRequest::StepBackward { current_ms } => {
+                let current_ms = current_ms.unwrap_or(self.requested_position_ms);
+                let previous_frame_ms = self.queue_previous_frame(current_ms)?;
+                self.requested_position_ms = previous_frame_ms;
+                if let Some(audio_requests) = audio_requests {
+                    audio_requests.lock().push_back(AudioRequest::SeekToMs {
+                        target_ms: previous_frame_ms,
+                    });
+                }
+            }
+            VideoRequest::SelectStream {
+                stream_index,
+                seek_frac,
+                seek_mode,
+            } => {
+                self.select_stream(stream_index)?;
+                self.frame_queue.lock().clear();
+                self.requested_position_ms = 0;
+                if let Some(seek_frac) = seek_frac {
+                    self.seek_into_queue(seek_frac, duration_ms, seek_mode)?;
+                }
+            }
         }
+        Ok(())
     }
 }
 
@@ -855,6 +3178,12 @@ impl Streamer for VideoStreamer {
     fn elapsed_ms(&mut self) -> &mut Cache<i64> {
         &mut self.video_elapsed_ms
     }
+    fn time_base(&self) -> Rational {
+        self.time_base
+    }
+    fn start_time(&self) -> i64 {
+        self.start_time
+    }
     fn decoder(&mut self) -> &mut ffmpeg::decoder::Opened {
         &mut self.video_decoder.0
     }
@@ -864,20 +3193,184 @@ impl Streamer for VideoStreamer {
     fn player_state(&mut self) -> &mut Cache<PlayerState> {
         &mut self.player_state
     }
+    fn read_until_ms(&mut self) -> &mut Cache<i64> {
+        &mut self.read_until_ms
+    }
+    fn ended(&mut self) -> &mut Cache<bool> {
+        &mut self.ended
+    }
     fn decode_frame(&mut self) -> Result<Self::Frame> {
         let mut decoded_frame = Video::empty();
-        self.video_decoder.receive_frame(&mut decoded_frame)?;
+        self.video_decoder
+            .receive_frame(&mut decoded_frame)
+            .map_err(Error::Decode)?;
+        self.set_elapsed_from_frame(&decoded_frame);
         Ok(decoded_frame)
     }
     fn process_frame(&mut self, frame: Self::Frame) -> Result<Self::ProcessedFrame> {
         let mut rgb_frame = Video::empty();
-        self.scaler.run(&frame, &mut rgb_frame)?;
+        self.scaler
+            .run(&frame, &mut rgb_frame)
+            .map_err(Error::Decode)?;
 
         let image = video_frame_to_image(rgb_frame);
         Ok(image)
     }
 }
 
+impl AudioStreamer {
+    /// Decode another audio stream of the input from now on, resampled to the same output as before.
+    fn select_stream(&mut self, stream_index: usize) -> Result<()> {
+        let stream = self
+            .input_context
+            .stream(stream_index)
+            .ok_or(Error::Probe(ffmpeg::Error::StreamNotFound))?;
+        let time_base = stream.time_base();
+        let start_time = stream_start_time(&stream);
+        let audio_context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+            .map_err(Error::Probe)?;
+        let audio_decoder = audio_context
+            .decoder()
+            .audio()
+            .map_err(|e| Error::UnsupportedFormat(format!("failed to open audio decoder: {e}")))?;
+        let output = *self.resampler.output();
+        self.resampler = audio_resampler(&audio_decoder, output.channel_layout, output.rate)?;
+        if self.channel_mixing != ChannelMixing::default() {
+            audio::apply_channel_mixing(&mut self.resampler, &self.channel_mixing)?;
+        }
+        self.audio_decoder = audio_decoder;
+        self.audio_stream_index = stream_index;
+        self.time_base = time_base;
+        self.start_time = start_time;
+        // the tempo filter is rebuilt for the new decoder's format when it's next needed
+        self.tempo_filter = None;
+        Ok(())
+    }
+    fn set_channel_mixing(&mut self, channel_mixing: ChannelMixing) -> Result<()> {
+        audio::apply_channel_mixing(&mut self.resampler, &channel_mixing)?;
+        self.channel_mixing = channel_mixing;
+        Ok(())
+    }
+    /// Resample a frame and push its samples into the sample buffer. Whatever doesn't fit is kept until
+    /// there's space for it (see [`AudioStreamer::flush_pending_samples`]).
+    fn queue_samples(&mut self, frame: &Audio) -> Result<()> {
+        let mut resampled_frame = Audio::empty();
+        self.resampler
+            .run(frame, &mut resampled_frame)
+            .map_err(Error::Decode)?;
+        let audio_samples = if resampled_frame.is_packed() {
+            packed(&resampled_frame)?
+        } else {
+            resampled_frame.plane(0)
+        };
+        self.pending_samples.extend_from_slice(audio_samples);
+        self.flush_pending_samples();
+        Ok(())
+    }
+    /// Push as many of the pending samples into the sample buffer as fit, once they're due. Returns whether
+    /// all of them were pushed.
+    fn flush_pending_samples(&mut self) -> bool {
+        if let Some(pending_start_ms) = self.pending_start_ms {
+            let buffered_ms = self.samples_ms(self.audio_sample_producer.len());
+            if pending_start_ms - self.sync_tolerance_ms > self.clock.elapsed_ms() + buffered_ms {
+                return false;
+            }
+            self.pending_start_ms = None;
+        }
+        let pushed = self.audio_sample_producer.push_slice(&self.pending_samples);
+        self.pending_samples.drain(..pushed);
+        self.pending_samples.is_empty()
+    }
+    /// Whether any samples are left to be heard.
+    fn has_queued_samples(&self) -> bool {
+        !self.audio_sample_producer.is_empty() || !self.pending_samples.is_empty()
+    }
+    /// Do the audio thread's next piece of work: seek (once per target while the seekbar is dragged), queue
+    /// the samples left over from the last frame, or decode the next frame. Returns whether the thread
+    /// should wait before the next step.
+    fn decode_step(
+        &mut self,
+        duration_ms: i64,
+        seek_mode: SeekMode,
+        last_seek_frac: &mut Option<f32>,
+    ) -> Result<bool> {
+        match self.player_state.get() {
+            PlayerState::Seeking(seek_frac) => {
+                if *last_seek_frac != Some(seek_frac) {
+                    *last_seek_frac = Some(seek_frac);
+                    self.seek(seek_frac, duration_ms, seek_mode, false, |_| {})?;
+                    self.discard_queued_samples();
+                }
+                Ok(true)
+            }
+            player_state => {
+                *last_seek_frac = None;
+                // nothing new is decoded until the samples of the last frame are all queued
+                if !self.flush_pending_samples() {
+                    return Ok(true);
+                }
+                self.process_state(duration_ms, seek_mode, false, |_| {})?;
+                Ok(!is_audible_state(player_state))
+            }
+        }
+    }
+    /// Carry out a request from the ui (or the decode thread), on the audio thread.
+    fn handle_request(&mut self, audio_request: AudioRequest, duration_ms: i64) -> Result<()> {
+        match audio_request {
+            AudioRequest::Reset => self.reset(false),
+            AudioRequest::Seek {
+                seek_frac,
+                seek_mode,
+            } => {
+                self.seek(seek_frac, duration_ms, seek_mode, false, |_| {})?;
+                self.discard_queued_samples();
+                self.audio_clock_ms.set(None);
+            }
+            AudioRequest::SeekToMs { target_ms } => self.seek_to_ms(target_ms)?,
+            AudioRequest::SelectStream {
+                stream_index,
+                seek_ms,
+            } => {
+                self.select_stream(stream_index)?;
+                if let Some(seek_ms) = seek_ms {
+                    self.seek_to_ms(seek_ms)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Seek accurately to `target_ms`, dropping what was queued, to keep in step with a video frame that
+    /// was shown without playing up to it.
+    fn seek_to_ms(&mut self, target_ms: i64) -> Result<()> {
+        self.seek_ms(target_ms, SeekMode::Accurate, false, |_| {})?;
+        self.discard_queued_samples();
+        self.audio_clock_ms.set(None);
+        Ok(())
+    }
+    /// Fade out and drop the samples queued from before a seek.
+    fn discard_queued_samples(&mut self) {
+        self.pending_samples.clear();
+        self.pending_start_ms = None;
+        self.stream_handle.discard(self.audio_sample_producer.len());
+    }
+    /// The stream's handle in the mixer of the [`AudioBackend`] it plays through, for its gain, pan, mute
+    /// and levels.
+    pub fn stream_handle(&self) -> &AudioStreamHandle {
+        &self.stream_handle
+    }
+    /// How much of the stream the samples waiting in the sample buffer (or to be pushed into it) cover, in
+    /// milliseconds.
+    fn buffered_ms(&mut self) -> i64 {
+        self.samples_ms(self.audio_sample_producer.len() + self.pending_samples.len())
+    }
+    /// How much of the stream a number of output samples cover, in milliseconds.
+    fn samples_ms(&mut self, sample_count: usize) -> i64 {
+        let frames = sample_count / self.output_channels.max(1);
+        let ms = (frames as i64 * 1000) / self.output_rate.max(1) as i64;
+        (ms as f32 * self.playback_speed.get()) as i64
+    }
+}
+
 impl Streamer for AudioStreamer {
     type Frame = Audio;
     type ProcessedFrame = ();
@@ -887,6 +3380,12 @@ impl Streamer for AudioStreamer {
     fn elapsed_ms(&mut self) -> &mut Cache<i64> {
         &mut self.audio_elapsed_ms
     }
+    fn time_base(&self) -> Rational {
+        self.time_base
+    }
+    fn start_time(&self) -> i64 {
+        self.start_time
+    }
     fn decoder(&mut self) -> &mut ffmpeg::decoder::Opened {
         &mut self.audio_decoder.0
     }
@@ -896,109 +3395,92 @@ impl Streamer for AudioStreamer {
     fn player_state(&mut self) -> &mut Cache<PlayerState> {
         &mut self.player_state
     }
+    fn read_until_ms(&mut self) -> &mut Cache<i64> {
+        &mut self.read_until_ms
+    }
+    fn ended(&mut self) -> &mut Cache<bool> {
+        &mut self.ended
+    }
     fn decode_frame(&mut self) -> Result<Self::Frame> {
         let mut decoded_frame = Audio::empty();
-        self.audio_decoder.receive_frame(&mut decoded_frame)?;
+        self.audio_decoder
+            .receive_frame(&mut decoded_frame)
+            .map_err(Error::Decode)?;
+        self.set_elapsed_from_frame(&decoded_frame);
         Ok(decoded_frame)
     }
     fn process_frame(&mut self, frame: Self::Frame) -> Result<Self::ProcessedFrame> {
-        let mut resampled_frame = Audio::empty();
-        self.resampler.run(&frame, &mut resampled_frame)?;
-        let audio_samples = if resampled_frame.is_packed() {
-            packed(&resampled_frame)
-        } else {
-            resampled_frame.plane(0)
-        };
-        while self.audio_sample_producer.free_len() < audio_samples.len() {
-            // std::thread::sleep(std::time::Duration::from_millis(10));
+        let frame_start_ms = self.audio_elapsed_ms.get();
+        let frame_end_ms =
+            frame_start_ms + (frame.samples() as i64 * 1000) / frame.rate().max(1) as i64;
+
+        if matches!(self.sync_mode, SyncMode::VideoMaster | SyncMode::External) {
+            // where the playback clock will be once the samples waiting in the sample buffer have been heard
+            let clock_ms_when_heard = self.clock.elapsed_ms() + self.buffered_ms();
+            if frame_start_ms + self.sync_tolerance_ms < clock_ms_when_heard {
+                // too far behind the clock, drop the frame
+                return Ok(());
+            }
+            if frame_start_ms - self.sync_tolerance_ms > clock_ms_when_heard {
+                // too far ahead of the clock: the samples wait in `pending_samples` until it catches up
+                self.pending_start_ms = Some(frame_start_ms);
+            }
         }
-        self.audio_sample_producer.push_slice(audio_samples);
-        Ok(())
-    }
-}
-
-type FfmpegAudioFormat = ffmpeg::format::Sample;
-type FfmpegAudioFormatType = ffmpeg::format::sample::Type;
-trait AsFfmpegSample {
-    fn to_sample(&self) -> ffmpeg::format::Sample;
-}
 
-impl AsFfmpegSample for AudioFormat {
-    fn to_sample(&self) -> FfmpegAudioFormat {
-        match self {
-            AudioFormat::U8 => FfmpegAudioFormat::U8(FfmpegAudioFormatType::Packed),
-            AudioFormat::S8 => panic!("unsupported audio format"),
-            AudioFormat::U16LSB => panic!("unsupported audio format"),
-            AudioFormat::U16MSB => panic!("unsupported audio format"),
-            AudioFormat::S16LSB => FfmpegAudioFormat::I16(FfmpegAudioFormatType::Packed),
-            AudioFormat::S16MSB => FfmpegAudioFormat::I16(FfmpegAudioFormatType::Packed),
-            AudioFormat::S32LSB => FfmpegAudioFormat::I32(FfmpegAudioFormatType::Packed),
-            AudioFormat::S32MSB => FfmpegAudioFormat::I32(FfmpegAudioFormatType::Packed),
-            AudioFormat::F32LSB => FfmpegAudioFormat::F32(FfmpegAudioFormatType::Packed),
-            AudioFormat::F32MSB => FfmpegAudioFormat::F32(FfmpegAudioFormatType::Packed),
+        let playback_speed = self.playback_speed.get();
+        if playback_speed == 1. {
+            self.tempo_filter = None;
+            self.queue_samples(&frame)?;
+        } else {
+            if self.tempo_filter.as_ref().map(|f| f.speed) != Some(playback_speed) {
+                self.tempo_filter = Some(TempoFilter::new(&self.audio_decoder, playback_speed)?);
+            }
+            if let Some(tempo_filter) = self.tempo_filter.as_mut() {
+                for filtered_frame in tempo_filter.filter(&frame)? {
+                    self.queue_samples(&filtered_frame)?;
+                }
+            }
         }
+        self.queued_until_ms = frame_end_ms;
+        let audio_clock_ms = self.queued_until_ms - self.buffered_ms();
+        self.audio_clock_ms.set(Some(audio_clock_ms));
+        Ok(())
     }
 }
 
-/// Create a new [`AudioDeviceCallback`]. Required for using audio.
-pub fn init_audio_device(audio_sys: &sdl2::AudioSubsystem) -> Result<AudioDevice, String> {
-    AudioDeviceCallback::init(audio_sys)
-}
-
-/// Pipes audio samples to SDL2.
-pub struct AudioDeviceCallback {
-    sample_streams: Vec<AudioSampleStream>,
-}
-
-struct AudioSampleStream {
-    sample_consumer: AudioSampleConsumer,
-    audio_volume: Cache<f32>,
+/// Whether audio is heard in a state, rather than faded out.
+fn is_audible_state(player_state: PlayerState) -> bool {
+    matches!(player_state, PlayerState::Playing | PlayerState::EndOfFile)
 }
 
-impl AudioCallback for AudioDeviceCallback {
-    type Channel = f32;
-    fn callback(&mut self, output: &mut [Self::Channel]) {
-        for x in output.iter_mut() {
-            *x = self
-                .sample_streams
-                .iter_mut()
-                .map(|s| s.sample_consumer.pop().unwrap_or(0.) * s.audio_volume.get())
-                .sum()
-        }
-    }
-}
-
-impl AudioDeviceCallback {
-    fn init(audio_sys: &sdl2::AudioSubsystem) -> Result<AudioDevice, String> {
-        let audio_spec = AudioSpecDesired {
-            freq: Some(44_100),
-            channels: Some(2),
-            samples: None,
-        };
-        let device = audio_sys.open_playback(None, &audio_spec, |_spec| AudioDeviceCallback {
-            sample_streams: vec![],
-        })?;
-        Ok(device)
-    }
+#[cfg(feature = "sdl2")]
+/// Open the default SDL2 playback device. Required for using audio through SDL2.
+pub fn init_audio_device(audio_sys: &sdl2::AudioSubsystem) -> Result<AudioDevice> {
+    audio::SdlBackend::new(audio_sys)
 }
 
 #[inline]
 // Thanks https://github.com/zmwangx/rust-ffmpeg/issues/72 <3
 // Interpret the audio frame's data as packed (alternating channels, 12121212, as opposed to planar 11112222)
-fn packed<T: ffmpeg::frame::audio::Sample>(frame: &Audio) -> &[T] {
+fn packed<T: ffmpeg::frame::audio::Sample>(frame: &Audio) -> Result<&[T]> {
     if !frame.is_packed() {
-        panic!("data is not packed");
+        return Err(Error::UnsupportedFormat(
+            "audio data is not packed".to_string(),
+        ));
     }
 
     if !<T as ffmpeg::frame::audio::Sample>::is_valid(frame.format(), frame.channels()) {
-        panic!("unsupported type");
+        return Err(Error::UnsupportedFormat(format!(
+            "audio sample format {:?}",
+            frame.format()
+        )));
     }
 
     unsafe {
-        std::slice::from_raw_parts(
+        Ok(std::slice::from_raw_parts(
             (*frame.as_ptr()).data[0] as *const T,
             frame.samples() * frame.channels() as usize,
-        )
+        ))
     }
 }
 
@@ -1023,6 +3505,95 @@ fn video_frame_to_image(frame: Video) -> ColorImage {
     ColorImage { size, pixels }
 }
 
-// pub fn init() {
-//     ffmpeg::init().unwrap();
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_thread_errors_are_reported_once() {
+        let events = EventSenders::default();
+        let receiver = events.subscribe();
+        let mut errors = ThreadErrors::default();
+        let e = Error::Decode(ffmpeg::Error::InvalidData);
+        let backoffs = (0..6)
+            .map(|_| errors.report(&events, e.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(receiver.try_iter().count(), 1);
+        assert_eq!(
+            backoffs[..3],
+            [10, 20, 40].map(std::time::Duration::from_millis)
+        );
+        assert_eq!(backoffs[5], MAX_ERROR_BACKOFF);
+
+        // another error, or the same one after something got done, is reported again
+        let e = Error::Seek(ffmpeg::Error::InvalidData);
+        errors.report(&events, e.clone());
+        errors.clear();
+        errors.report(&events, e.clone());
+        assert_eq!(receiver.try_iter().count(), 2);
+        assert_eq!(*events.last_error.lock(), Some(e));
+    }
+
+    #[test]
+    fn subtitles_are_delayed_by_the_offset() {
+        let ctx = egui::Context::default();
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gray_ramp_10fps_2s.y4m"
+        );
+        let mut player = Player::new(&ctx, fixture, PlayerConfig::default()).unwrap();
+        player.video_elapsed_ms.set(1000);
+        assert_eq!(player.subtitle_position_ms(), 1000);
+        player.set_subtitle_offset(Duration::milliseconds(400));
+        assert_eq!(player.subtitle_position_ms(), 600);
+        player.set_subtitle_offset(Duration::milliseconds(-1500));
+        assert_eq!(player.subtitle_position_ms(), 2500);
+        assert_eq!(player.subtitle_offset(), Duration::milliseconds(-1500));
+    }
+    #[test]
+    fn large_tempo_changes_are_chained() {
+        assert_eq!(atempo_factors(1.), [1.]);
+        assert_eq!(atempo_factors(2.), [2.]);
+        assert_eq!(atempo_factors(3.), [2., 1.5]);
+        assert_eq!(atempo_factors(4.), [2., 2.]);
+        assert_eq!(atempo_factors(0.5), [0.5]);
+        assert_eq!(atempo_factors(0.25), [0.5, 0.5]);
+        assert_eq!(atempo_factors(0.3), [0.5, 0.6]);
+    }
+
+    /// How many samples come out of a tempo filter at `speed` for `input_samples` mono samples at 44.1 kHz.
+    fn tempo_filtered_samples(speed: f32, input_samples: usize) -> usize {
+        let format = ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed);
+        let mut tempo_filter = TempoFilter {
+            graph: TempoFilter::build_graph(44100, format, ChannelLayout::MONO, speed).unwrap(),
+            speed,
+        };
+        let mut output_samples = 0;
+        for start in (0..input_samples).step_by(1024) {
+            let frame_samples = 1024.min(input_samples - start);
+            let mut frame = Audio::new(format, frame_samples, ChannelLayout::MONO);
+            frame.set_rate(44100);
+            frame.set_pts(Some(start as i64));
+            for (j, sample) in frame.plane_mut::<f32>(0).iter_mut().enumerate() {
+                let t = (start + j) as f32 / 44100.;
+                *sample = (t * 440. * std::f32::consts::TAU).sin() * 0.5;
+            }
+            let filtered = tempo_filter.filter(&frame).unwrap();
+            output_samples += filtered.iter().map(|frame| frame.samples()).sum::<usize>();
+        }
+        output_samples
+    }
+
+    #[test]
+    fn tempo_filter_changes_the_duration() {
+        // atempo holds back part of a window of audio, until more comes or the stream ends
+        for speed in [0.5, 2., 4.] {
+            let input_samples = (44100. * speed) as usize;
+            let output_samples = tempo_filtered_samples(speed, input_samples);
+            assert!(
+                output_samples.abs_diff(44100) < 44100 / 10,
+                "{output_samples} samples out at {speed}x"
+            );
+        }
+    }
+}
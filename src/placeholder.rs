@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+use egui::{pos2, vec2, Color32, ColorImage, Rect, Rounding, Ui};
+use ffmpeg::util::frame::video::Video;
+
+use crate::error::{Error, Result};
+use crate::io::MediaSource;
+use crate::{open_video_decoder, rgb_scaler, video_frame_to_image};
+
+/// How many level readings the visualizer shows across its width.
+const VISUALIZER_HISTORY: usize = 96;
+
+/// Decode the embedded cover art (the attached picture stream) of audio-only media.
+pub(crate) fn decode_cover_art(
+    media_source: &dyn MediaSource,
+    stream_index: usize,
+) -> Result<ColorImage> {
+    let mut input_context = media_source.open()?;
+    let (mut video_decoder, _, _) = open_video_decoder(&input_context, stream_index)?;
+    // the picture is the stream's only packet
+    let (_, packet) = input_context
+        .packets()
+        .find(|(stream, _)| stream.index() == stream_index)
+        .ok_or(Error::Decode(ffmpeg::Error::Eof))?;
+    video_decoder.send_packet(&packet).map_err(Error::Decode)?;
+    video_decoder.send_eof().map_err(Error::Decode)?;
+    let mut decoded_frame = Video::empty();
+    video_decoder
+        .receive_frame(&mut decoded_frame)
+        .map_err(Error::Decode)?;
+    let mut scaler = rgb_scaler(
+        &video_decoder,
+        video_decoder.width(),
+        video_decoder.height(),
+    )?;
+    let mut rgb_frame = Video::empty();
+    scaler
+        .run(&decoded_frame, &mut rgb_frame)
+        .map_err(Error::Decode)?;
+    Ok(video_frame_to_image(rgb_frame))
+}
+
+#[derive(Default)]
+/// Shown in place of the video of audio-only media without cover art: the recent audio levels, scrolling
+/// from right to left.
+pub(crate) struct Visualizer {
+    levels: VecDeque<f32>,
+}
+
+impl Visualizer {
+    /// Add a reading of the peak levels of each channel.
+    pub(crate) fn push_levels(&mut self, peak_levels: &[f32]) {
+        let level = peak_levels.iter().copied().fold(0., f32::max);
+        self.levels.push_back(level.clamp(0., 1.));
+        while self.levels.len() > VISUALIZER_HISTORY {
+            self.levels.pop_front();
+        }
+    }
+
+    /// Paint the visualizer over `rect`.
+    pub(crate) fn paint(&self, ui: &mut Ui, rect: Rect) {
+        let painter = ui.painter();
+        painter.rect_filled(rect, Rounding::none(), Color32::BLACK);
+        let bar_spacing = rect.width() / VISUALIZER_HISTORY as f32;
+        let bar_width = (bar_spacing * 0.6).max(1.);
+        // the newest reading is at the right edge
+        let first_bar = VISUALIZER_HISTORY - self.levels.len();
+        for (i, level) in self.levels.iter().enumerate() {
+            let bar_height = (level.sqrt() * rect.height() * 0.8).max(1.);
+            let bar_center = pos2(
+                rect.left() + (first_bar + i) as f32 * bar_spacing + bar_spacing / 2.,
+                rect.center().y,
+            );
+            painter.rect_filled(
+                Rect::from_center_size(bar_center, vec2(bar_width, bar_height)),
+                Rounding::same(bar_width / 2.),
+                Color32::from_white_alpha(100 + (level * 155.) as u8),
+            );
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use ffmpeg::ffi::{av_display_rotation_get, AV_NOPTS_VALUE};
+use ffmpeg::format::context::input::Input;
+use ffmpeg::format::{Pixel, Sample};
+use ffmpeg::media::Type;
+use ffmpeg::{ChannelLayout, DictionaryRef};
+
+use crate::error::{Error, Result};
+use crate::io::MediaSource;
+use crate::{timestamp_to_millisec, AV_TIME_BASE_RATIONAL};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Information about media, read from its container and stream headers without decoding anything.
+pub struct MediaInfo {
+    /// The duration of the media in ms, or `0` if unknown (such as for live streams).
+    pub duration_ms: i64,
+    /// The short name of the container format, such as `mov,mp4,m4a,3gp,3g2,mj2` or `matroska,webm`.
+    pub container: String,
+    /// The descriptive name of the container format.
+    pub container_description: String,
+    /// The overall bitrate in bits per second, or `0` if unknown.
+    pub bit_rate: i64,
+    /// The streams in the media.
+    pub streams: Vec<StreamInfo>,
+    /// The container's metadata tags.
+    pub tags: Vec<(String, String)>,
+    /// The chapters of the media.
+    pub chapters: Vec<ChapterInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Information about a stream in the media.
+pub struct StreamInfo {
+    /// The index of the stream in the media.
+    pub index: usize,
+    /// What kind of stream this is.
+    pub medium: Type,
+    /// The name of the stream's codec.
+    pub codec: String,
+    /// The stream's bitrate in bits per second, or `0` if unknown.
+    pub bit_rate: i64,
+    /// The duration of the stream in ms, if known.
+    pub duration_ms: Option<i64>,
+    /// The stream's language, if tagged.
+    pub language: Option<String>,
+    /// The stream's title, if tagged.
+    pub title: Option<String>,
+    /// The stream's metadata tags.
+    pub tags: Vec<(String, String)>,
+    /// Properties specific to the kind of stream.
+    pub details: StreamDetails,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Properties specific to the kind of a [`StreamInfo`].
+pub enum StreamDetails {
+    /// A video stream.
+    Video {
+        /// The width of the frames, in pixels.
+        width: u32,
+        /// The height of the frames, in pixels.
+        height: u32,
+        /// The average frame rate (or the base frame rate, for variable frame rate streams).
+        frame_rate: f64,
+        /// The name of the pixel format, such as `yuv420p`.
+        pixel_format: Option<String>,
+        /// How far the video should be rotated counterclockwise for display, in degrees.
+        rotation: f64,
+    },
+    /// An audio stream.
+    Audio {
+        /// The sample rate, in Hz.
+        sample_rate: u32,
+        /// The number of channels.
+        channels: u16,
+        /// The layout of the channels. Empty if unspecified.
+        channel_layout: ChannelLayout,
+        /// The name of the sample format, such as `fltp`.
+        sample_format: Option<String>,
+    },
+    /// Any other kind of stream (subtitles, data, attachments).
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A chapter of the media.
+pub struct ChapterInfo {
+    /// When the chapter starts, in ms.
+    pub start_ms: i64,
+    /// When the chapter ends, in ms.
+    pub end_ms: i64,
+    /// The chapter's title, if tagged.
+    pub title: Option<String>,
+}
+
+impl MediaInfo {
+    /// Read information about the media from a [`MediaSource`] (such as a [`std::path::PathBuf`]).
+    pub fn probe(media_source: impl MediaSource) -> Result<Self> {
+        Self::from_input(&media_source.open()?)
+    }
+
+    /// Read information about the media from an opened input.
+    pub(crate) fn from_input(input: &Input) -> Result<Self> {
+        let duration_ms = timestamp_to_millisec(input.duration().max(0), AV_TIME_BASE_RATIONAL);
+        let streams = input
+            .streams()
+            .map(|stream| StreamInfo::from_stream(&stream))
+            .collect::<Result<Vec<_>>>()?;
+        let chapters = input
+            .chapters()
+            .map(|chapter| ChapterInfo {
+                start_ms: timestamp_to_millisec(chapter.start(), chapter.time_base()),
+                end_ms: timestamp_to_millisec(chapter.end(), chapter.time_base()),
+                title: chapter.metadata().get("title").map(String::from),
+            })
+            .collect();
+        Ok(Self {
+            duration_ms,
+            container: input.format().name().to_string(),
+            container_description: input.format().description().to_string(),
+            bit_rate: input.bit_rate(),
+            streams,
+            tags: collect_tags(input.metadata()),
+            chapters,
+        })
+    }
+
+    /// The first video stream, if there is one.
+    pub fn video_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.medium == Type::Video)
+    }
+
+    /// The first audio stream, if there is one.
+    pub fn audio_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.medium == Type::Audio)
+    }
+}
+
+impl StreamInfo {
+    fn from_stream(stream: &ffmpeg::Stream) -> Result<Self> {
+        let parameters = stream.parameters();
+        let medium = parameters.medium();
+        // the codec context is only filled in from the parameters, never opened
+        let codec_context =
+            ffmpeg::codec::context::Context::from_parameters(parameters).map_err(Error::Probe)?;
+        let raw_context = unsafe { &*codec_context.as_ptr() };
+
+        let details = match medium {
+            Type::Video => {
+                let frame_rate = if stream.avg_frame_rate().numerator() > 0 {
+                    stream.avg_frame_rate()
+                } else {
+                    stream.rate()
+                };
+                StreamDetails::Video {
+                    width: raw_context.width.max(0) as u32,
+                    height: raw_context.height.max(0) as u32,
+                    frame_rate: frame_rate.numerator() as f64
+                        / frame_rate.denominator().max(1) as f64,
+                    pixel_format: Pixel::from(raw_context.pix_fmt)
+                        .descriptor()
+                        .map(|d| d.name().to_string()),
+                    rotation: stream_rotation(stream),
+                }
+            }
+            Type::Audio => {
+                let sample_format = Sample::from(raw_context.sample_fmt);
+                StreamDetails::Audio {
+                    sample_rate: raw_context.sample_rate.max(0) as u32,
+                    channels: raw_context.channels.max(0) as u16,
+                    channel_layout: ChannelLayout::from_bits_truncate(raw_context.channel_layout),
+                    sample_format: (sample_format != Sample::None)
+                        .then(|| sample_format.name().to_string()),
+                }
+            }
+            _ => StreamDetails::Other,
+        };
+
+        let metadata = stream.metadata();
+        let duration_ms = match stream.duration() {
+            AV_NOPTS_VALUE => None,
+            duration => Some(timestamp_to_millisec(duration, stream.time_base())),
+        };
+        Ok(Self {
+            index: stream.index(),
+            medium,
+            codec: codec_context.id().name().to_string(),
+            bit_rate: raw_context.bit_rate,
+            duration_ms,
+            language: metadata.get("language").map(String::from),
+            title: metadata.get("title").map(String::from),
+            tags: collect_tags(metadata),
+            details,
+        })
+    }
+}
+
+/// How far a video stream should be rotated counterclockwise for display, in degrees, from its display
+/// matrix or (for older files) its `rotate` tag.
+fn stream_rotation(stream: &ffmpeg::Stream) -> f64 {
+    let display_matrix = stream
+        .side_data()
+        .find(|side_data| side_data.kind() == ffmpeg::packet::side_data::Type::DisplayMatrix);
+    rotation(
+        display_matrix
+            .as_ref()
+            .map(|display_matrix| display_matrix.data()),
+        stream.metadata().get("rotate"),
+    )
+}
+
+/// See [`stream_rotation`]. The display matrix is given as the bytes of its side data.
+fn rotation(display_matrix: Option<&[u8]>, rotate_tag: Option<&str>) -> f64 {
+    if let Some(data) = display_matrix {
+        if data.len() >= 9 * std::mem::size_of::<i32>() {
+            let rotation = unsafe { av_display_rotation_get(data.as_ptr() as *const i32) };
+            if rotation.is_finite() {
+                // `av_display_rotation_get` gives -0 for no rotation
+                return rotation + 0.;
+            }
+        }
+    }
+    rotate_tag
+        .and_then(|rotate| rotate.parse::<f64>().ok())
+        // the tag is clockwise
+        .map(|rotate| -rotate)
+        .unwrap_or(0.)
+}
+
+fn collect_tags(metadata: DictionaryRef) -> Vec<(String, String)> {
+    metadata
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::ffi::av_display_rotation_set;
+
+    use super::*;
+
+    fn display_matrix(angle: f64) -> Vec<u8> {
+        let mut matrix = [0i32; 9];
+        unsafe { av_display_rotation_set(matrix.as_mut_ptr(), angle) };
+        matrix
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect()
+    }
+
+    fn assert_degrees(degrees: f64, expected: f64) {
+        assert!((degrees - expected).abs() < 1e-6, "{degrees} != {expected}");
+    }
+
+    #[test]
+    fn rotation_comes_from_the_display_matrix() {
+        assert_degrees(rotation(Some(&display_matrix(90.)), None), 90.);
+        assert_degrees(rotation(Some(&display_matrix(-90.)), Some("0")), -90.);
+        let unrotated = rotation(Some(&display_matrix(0.)), None);
+        assert!(unrotated == 0. && unrotated.is_sign_positive());
+    }
+
+    #[test]
+    fn rotation_falls_back_to_the_clockwise_tag() {
+        assert_eq!(rotation(None, Some("90")), -90.);
+        assert_eq!(rotation(None, Some("180")), -180.);
+        // a matrix too short to be one is ignored
+        assert_eq!(rotation(Some(&[0; 4]), Some("270")), -270.);
+        assert_eq!(rotation(None, Some("sideways")), 0.);
+        assert_eq!(rotation(None, None), 0.);
+    }
+}
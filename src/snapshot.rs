@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use egui::ColorImage;
+use ffmpeg::codec::Id;
+use ffmpeg::format::Pixel;
+use ffmpeg::util::frame::video::Video;
+use ffmpeg::{software, Packet};
+
+use crate::error::{Error, Result};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// The image formats frames can be saved as.
+pub enum ImageFormat {
+    /// Lossless PNG.
+    Png,
+    /// JPEG, at ffmpeg's default quality.
+    Jpeg,
+    /// Uncompressed BMP.
+    Bmp,
+}
+
+impl ImageFormat {
+    fn codec_and_pixel_format(&self) -> (Id, Pixel) {
+        match self {
+            ImageFormat::Png => (Id::PNG, Pixel::RGB24),
+            ImageFormat::Jpeg => (Id::MJPEG, Pixel::YUVJ420P),
+            ImageFormat::Bmp => (Id::BMP, Pixel::BGR24),
+        }
+    }
+}
+
+/// Encode an image with ffmpeg's image encoders. Transparency is discarded.
+pub fn encode_image(image: &ColorImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let [width, height] = [image.size[0] as u32, image.size[1] as u32];
+    if width == 0 || height == 0 {
+        return Err(Error::UnsupportedFormat(format!(
+            "can't encode a {width}x{height} image"
+        )));
+    }
+    let mut rgb_frame = Video::new(Pixel::RGB24, width, height);
+    let stride = rgb_frame.stride(0);
+    let data = rgb_frame.data_mut(0);
+    for (y, row) in image.pixels.chunks_exact(image.size[0]).enumerate() {
+        let line = &mut data[y * stride..y * stride + row.len() * 3];
+        for (pixel, color) in line.chunks_exact_mut(3).zip(row) {
+            pixel.copy_from_slice(&[color.r(), color.g(), color.b()]);
+        }
+    }
+
+    let (codec_id, pixel_format) = format.codec_and_pixel_format();
+    let frame = if pixel_format == Pixel::RGB24 {
+        rgb_frame
+    } else {
+        let mut scaler = software::scaling::Context::get(
+            Pixel::RGB24,
+            width,
+            height,
+            pixel_format,
+            width,
+            height,
+            software::scaling::flag::Flags::BILINEAR,
+        )
+        .map_err(|e| Error::UnsupportedFormat(format!("failed to create scaler: {e}")))?;
+        let mut converted_frame = Video::empty();
+        scaler
+            .run(&rgb_frame, &mut converted_frame)
+            .map_err(Error::Encode)?;
+        converted_frame
+    };
+
+    let codec = ffmpeg::encoder::find(codec_id)
+        .ok_or_else(|| Error::UnsupportedFormat(format!("no encoder for {format:?}")))?;
+    let mut encoder = ffmpeg::codec::context::Context::new()
+        .encoder()
+        .video()
+        .map_err(Error::Encode)?;
+    encoder.set_width(width);
+    encoder.set_height(height);
+    encoder.set_format(pixel_format);
+    encoder.set_time_base((1, 1));
+    let mut encoder = encoder.open_as(codec).map_err(Error::Encode)?;
+
+    encoder.send_frame(&frame).map_err(Error::Encode)?;
+    encoder.send_eof().map_err(Error::Encode)?;
+    let mut encoded = Vec::new();
+    let mut packet = Packet::empty();
+    while encoder.receive_packet(&mut packet).is_ok() {
+        if let Some(data) = packet.data() {
+            encoded.extend_from_slice(data);
+        }
+    }
+    if encoded.is_empty() {
+        return Err(Error::Encode(ffmpeg::Error::InvalidData));
+    }
+    Ok(encoded)
+}
+
+/// Encode an image (see [`encode_image`]) and write it to a file.
+pub fn save_image(image: &ColorImage, path: impl AsRef<Path>, format: ImageFormat) -> Result<()> {
+    let encoded = encode_image(image, format)?;
+    std::fs::write(path, encoded).map_err(|e| Error::Io(e.kind(), e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use egui::Color32;
+
+    use super::*;
+    use crate::placeholder::decode_cover_art;
+
+    const LEFT: Color32 = Color32::from_rgb(200, 40, 40);
+    const RIGHT: Color32 = Color32::from_rgb(40, 40, 200);
+
+    /// 32x16, with a left half and a right half of different colors.
+    fn two_halves() -> ColorImage {
+        let pixels = (0..16)
+            .flat_map(|_| (0..32).map(|x| if x < 16 { LEFT } else { RIGHT }))
+            .collect();
+        ColorImage {
+            size: [32, 16],
+            pixels,
+        }
+    }
+
+    fn round_trip(image: &ColorImage, format: ImageFormat) -> ColorImage {
+        let encoded: Arc<[u8]> = encode_image(image, format).unwrap().into();
+        decode_cover_art(&encoded, 0).unwrap()
+    }
+
+    fn assert_close(color: Color32, expected: Color32, tolerance: u8) {
+        let channels = |color: Color32| [color.r(), color.g(), color.b()];
+        for (channel, expected_channel) in channels(color).into_iter().zip(channels(expected)) {
+            assert!(
+                channel.abs_diff(expected_channel) <= tolerance,
+                "{color:?} is not within {tolerance} of {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn lossless_formats_round_trip_exactly() {
+        let image = two_halves();
+        for format in [ImageFormat::Png, ImageFormat::Bmp] {
+            assert_eq!(round_trip(&image, format), image, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn jpeg_round_trips_closely() {
+        let image = round_trip(&two_halves(), ImageFormat::Jpeg);
+        assert_eq!(image.size, [32, 16]);
+        // away from the edge between the halves, which chroma subsampling blurs
+        assert_close(image.pixels[8 * 32 + 4], LEFT, 12);
+        assert_close(image.pixels[8 * 32 + 28], RIGHT, 12);
+    }
+
+    #[test]
+    fn empty_images_are_not_encoded() {
+        let image = ColorImage::new([0, 16], Color32::BLACK);
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Bmp] {
+            assert!(matches!(
+                encode_image(&image, format),
+                Err(Error::UnsupportedFormat(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,695 @@
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use egui::text::{LayoutJob, TextFormat};
+use egui::{
+    pos2, vec2, Color32, ColorImage, FontId, Rect, Rounding, Stroke, TextureHandle, TextureOptions,
+    Ui,
+};
+use ffmpeg::codec::subtitle::{Bitmap, Rect as SubtitleRect};
+use ffmpeg::media::Type;
+use ffmpeg::{rescale, Packet};
+use parking_lot::Mutex;
+
+use crate::error::{Error, Result};
+use crate::io::MediaInput;
+use crate::{millisec_to_timestamp, stream_start_time, timestamp_to_millisec, EventSenders};
+
+/// How far ahead of the playback position the subtitle stream is read, in ms.
+const SUBTITLE_READ_AHEAD_MS: i64 = 10_000;
+
+/// How long the subtitle thread waits before checking again once it has read far enough ahead.
+const SUBTITLE_THREAD_IDLE_WAIT: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Debug, PartialEq)]
+/// A subtitle cue, shown from `start_ms` to `end_ms`.
+pub struct Subtitle {
+    /// When the cue is first shown, in ms.
+    pub start_ms: i64,
+    /// When the cue is hidden again, in ms.
+    pub end_ms: i64,
+    /// What the cue shows.
+    pub content: SubtitleContent,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// What a [`Subtitle`] shows.
+pub enum SubtitleContent {
+    /// Styled text, from text based formats (mov_text, subrip, webvtt, ass).
+    Text {
+        /// The styled runs of text making up the cue.
+        spans: Vec<SubtitleSpan>,
+        /// Whether the cue is shown at the top of the video, rather than at the bottom.
+        top_aligned: bool,
+    },
+    /// An image, from bitmap based formats (PGS, DVB, VobSub).
+    Bitmap(SubtitleBitmap),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A decoded bitmap subtitle, positioned on the video.
+pub struct SubtitleBitmap {
+    /// The subtitle's pixels.
+    pub image: Arc<ColorImage>,
+    /// Where the image is shown, in pixels of `canvas_size`.
+    pub rect: Rect,
+    /// The size of the canvas that `rect` is relative to, if the stream specifies one. Otherwise, `rect`
+    /// is relative to the video's size.
+    pub canvas_size: Option<[u32; 2]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+/// A run of subtitle text with the same style.
+pub struct SubtitleSpan {
+    /// The text. May contain line breaks.
+    pub text: String,
+    /// Whether the text is italic.
+    pub italic: bool,
+    /// Whether the text is bold.
+    pub bold: bool,
+    /// Whether the text is underlined.
+    pub underline: bool,
+}
+
+/// Decodes a subtitle stream on a background thread, a little ahead of the playback position, collecting
+/// its cues as they are decoded.
+pub(crate) struct SubtitleStreamer {
+    /// The embedded stream being decoded, or [`None`] for an external subtitle file.
+    pub(crate) stream_index: Option<usize>,
+    subtitles: Arc<Mutex<Vec<Subtitle>>>,
+    position_ms: Arc<AtomicI64>,
+    alive: Arc<AtomicBool>,
+}
+
+impl SubtitleStreamer {
+    /// Start decoding the subtitle stream `stream_index` of `input`, or its best subtitle stream if
+    /// [`None`] (as for external subtitle files).
+    pub(crate) fn spawn(
+        mut input: MediaInput,
+        stream_index: Option<usize>,
+        events: EventSenders,
+    ) -> Result<Self> {
+        let stream = match stream_index {
+            Some(stream_index) => input.stream(stream_index),
+            None => input.streams().best(Type::Subtitle),
+        }
+        .ok_or(Error::Probe(ffmpeg::Error::StreamNotFound))?;
+        let decoded_stream_index = stream.index();
+        let time_base = stream.time_base();
+        let start_time = stream_start_time(&stream);
+        let subtitle_context =
+            ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+                .map_err(Error::Probe)?;
+        let mut subtitle_decoder = subtitle_context.decoder().subtitle().map_err(|e| {
+            Error::UnsupportedFormat(format!("failed to open subtitle decoder: {e}"))
+        })?;
+
+        let subtitles = Arc::new(Mutex::new(Vec::new()));
+        let position_ms = Arc::new(AtomicI64::new(0));
+        let alive = Arc::new(AtomicBool::new(true));
+        let thread_subtitles = Arc::clone(&subtitles);
+        let thread_position_ms = Arc::clone(&position_ms);
+        let thread_alive = Arc::clone(&alive);
+        std::thread::spawn(move || {
+            let start_ms = timestamp_to_millisec(start_time, time_base);
+            // the part of the input that has been read, relative to the start of the stream
+            let mut read_from_ms = 0;
+            let mut read_until_ms = 0;
+            while thread_alive.load(Ordering::Relaxed) {
+                let position_ms = thread_position_ms.load(Ordering::Relaxed);
+                // after a jump (such as a seek) out of what's been read, reading continues from the new
+                // position instead of everything up to it
+                if position_ms < read_from_ms
+                    || position_ms > read_until_ms.saturating_add(SUBTITLE_READ_AHEAD_MS)
+                {
+                    let target_ts =
+                        millisec_to_timestamp(position_ms.max(0) + start_ms, rescale::TIME_BASE);
+                    if let Err(e) = input.seek(target_ts, ..target_ts) {
+                        events.emit_error(Error::Seek(e));
+                    }
+                    subtitle_decoder.flush();
+                    read_from_ms = position_ms;
+                    read_until_ms = position_ms;
+                }
+                if read_until_ms > position_ms.saturating_add(SUBTITLE_READ_AHEAD_MS) {
+                    std::thread::sleep(SUBTITLE_THREAD_IDLE_WAIT);
+                    continue;
+                }
+
+                let mut packet = Packet::empty();
+                match packet.read(&mut input) {
+                    Ok(()) => (),
+                    // nothing more is read until the next jump
+                    Err(ffmpeg::Error::Eof) => {
+                        read_until_ms = i64::MAX;
+                        continue;
+                    }
+                    Err(e) => {
+                        events.emit_error(Error::Decode(e));
+                        read_until_ms = i64::MAX;
+                        continue;
+                    }
+                }
+                let packet_time_base = input.stream(packet.stream()).map(|s| s.time_base());
+                if let (Some(timestamp), Some(packet_time_base)) =
+                    (packet.dts().or_else(|| packet.pts()), packet_time_base)
+                {
+                    let packet_ms = timestamp_to_millisec(timestamp, packet_time_base) - start_ms;
+                    read_until_ms = read_until_ms.max(packet_ms);
+                }
+                if packet.stream() != decoded_stream_index {
+                    continue;
+                }
+                let mut decoded = ffmpeg::Subtitle::new();
+                match subtitle_decoder.decode(&packet, &mut decoded) {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(e) => {
+                        events.emit_error(Error::Decode(e));
+                        continue;
+                    }
+                }
+                let Some(pts) = packet.pts() else {
+                    continue;
+                };
+                let packet_ms = timestamp_to_millisec(pts - start_time, time_base);
+                let start_ms = packet_ms + decoded.start() as i64;
+                // decoders that don't know when the cue ends leave it to the packet duration, or (as
+                // bitmap formats do) to the next cue
+                let end_ms = if decoded.end() > decoded.start() && decoded.end() != u32::MAX {
+                    packet_ms + decoded.end() as i64
+                } else if packet.duration() > 0 {
+                    packet_ms + timestamp_to_millisec(packet.duration(), time_base)
+                } else {
+                    i64::MAX
+                };
+                let (canvas_width, canvas_height) = unsafe {
+                    let decoder = subtitle_decoder.as_ptr();
+                    ((*decoder).width, (*decoder).height)
+                };
+                let canvas_size = (canvas_width > 0 && canvas_height > 0)
+                    .then_some([canvas_width as u32, canvas_height as u32]);
+
+                let mut subtitles = thread_subtitles.lock();
+                // an event (even one without anything to show) ends the cues that were left open
+                for subtitle in subtitles.iter_mut() {
+                    if subtitle.end_ms == i64::MAX && subtitle.start_ms < start_ms {
+                        subtitle.end_ms = start_ms;
+                    }
+                }
+                for rect in decoded.rects() {
+                    let content = match rect {
+                        SubtitleRect::Ass(ass) => {
+                            let (spans, top_aligned) = parse_ass_dialogue(ass.get());
+                            SubtitleContent::Text { spans, top_aligned }
+                        }
+                        SubtitleRect::Text(text) => SubtitleContent::Text {
+                            spans: plain_spans(text.get()),
+                            top_aligned: false,
+                        },
+                        SubtitleRect::Bitmap(bitmap) => match bitmap_to_image(&bitmap) {
+                            Some((image, rect)) => SubtitleContent::Bitmap(SubtitleBitmap {
+                                image: Arc::new(image),
+                                rect,
+                                canvas_size,
+                            }),
+                            None => continue,
+                        },
+                        _ => continue,
+                    };
+                    let position = subtitles.partition_point(|s: &Subtitle| s.start_ms <= start_ms);
+                    // cues are read again after jumping back
+                    let already_read = subtitles[..position]
+                        .iter()
+                        .rev()
+                        .take_while(|s| s.start_ms == start_ms)
+                        .any(|s| s.content == content);
+                    if !already_read {
+                        let subtitle = Subtitle {
+                            start_ms,
+                            end_ms,
+                            content,
+                        };
+                        subtitles.insert(position, subtitle);
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            stream_index,
+            subtitles,
+            position_ms,
+            alive,
+        })
+    }
+
+    /// Move the position that the stream is read ahead of, in ms.
+    pub(crate) fn set_position_ms(&self, position_ms: i64) {
+        self.position_ms.store(position_ms, Ordering::Relaxed);
+    }
+
+    /// The cues that are shown at `position_ms`.
+    pub(crate) fn subtitles_at(&self, position_ms: i64) -> Vec<Subtitle> {
+        let subtitles = self.subtitles.lock();
+        let shown_until = subtitles.partition_point(|s| s.start_ms <= position_ms);
+        subtitles[..shown_until]
+            .iter()
+            .filter(|s| s.end_ms > position_ms)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for SubtitleStreamer {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Convert a paletted subtitle bitmap to an image, along with where it's shown on the canvas.
+fn bitmap_to_image(bitmap: &Bitmap) -> Option<(ColorImage, Rect)> {
+    unsafe {
+        let rect = &*bitmap.as_ptr();
+        let (width, height) = (rect.w.max(0) as usize, rect.h.max(0) as usize);
+        if width == 0 || height == 0 || rect.data[0].is_null() || rect.data[1].is_null() {
+            return None;
+        }
+        // each pixel is an index into the palette of (native endian) ARGB colors
+        let palette =
+            std::slice::from_raw_parts(rect.data[1] as *const u32, rect.nb_colors.max(0) as usize);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row =
+                std::slice::from_raw_parts(rect.data[0].add(y * rect.linesize[0] as usize), width);
+            pixels.extend(row.iter().map(|index| {
+                let argb = palette.get(*index as usize).copied().unwrap_or(0);
+                Color32::from_rgba_unmultiplied(
+                    (argb >> 16) as u8,
+                    (argb >> 8) as u8,
+                    argb as u8,
+                    (argb >> 24) as u8,
+                )
+            }));
+        }
+        let image = ColorImage {
+            size: [width, height],
+            pixels,
+        };
+        let position = pos2(rect.x as f32, rect.y as f32);
+        Some((
+            image,
+            Rect::from_min_size(position, vec2(width as f32, height as f32)),
+        ))
+    }
+}
+
+fn plain_spans(text: &str) -> Vec<SubtitleSpan> {
+    vec![SubtitleSpan {
+        text: text.trim_end().to_string(),
+        ..Default::default()
+    }]
+}
+
+/// Parse the text of an ASS dialogue event (which is what ffmpeg decodes all text subtitle formats to)
+/// into styled spans, and whether the event is aligned to the top of the video.
+fn parse_ass_dialogue(dialogue: &str) -> (Vec<SubtitleSpan>, bool) {
+    // "ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text", or the full event line in
+    // older versions of ffmpeg, which has one more field before the text
+    let (dialogue, field_count) = match dialogue.strip_prefix("Dialogue:") {
+        Some(dialogue) => (dialogue, 10),
+        None => (dialogue, 9),
+    };
+    let text = dialogue.splitn(field_count, ',').last().unwrap_or_default();
+
+    let mut spans = Vec::new();
+    let mut span = SubtitleSpan::default();
+    let mut top_aligned = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let block: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let mut style = SubtitleSpan {
+                    text: String::new(),
+                    ..span.clone()
+                };
+                for tag in block.split('\\').map(str::trim) {
+                    match tag {
+                        "i1" => style.italic = true,
+                        "i0" => style.italic = false,
+                        "b1" => style.bold = true,
+                        "b0" => style.bold = false,
+                        "u1" => style.underline = true,
+                        "u0" => style.underline = false,
+                        "r" => style = SubtitleSpan::default(),
+                        "an7" | "an8" | "an9" => top_aligned = true,
+                        _ => (),
+                    }
+                }
+                if !span.text.is_empty() {
+                    spans.push(std::mem::take(&mut span));
+                }
+                span = style;
+            }
+            '\\' => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    span.text.push('\n');
+                }
+                Some('h') => {
+                    chars.next();
+                    span.text.push(' ');
+                }
+                _ => span.text.push(c),
+            },
+            c => span.text.push(c),
+        }
+    }
+    if !span.text.is_empty() {
+        spans.push(span);
+    }
+    (spans, top_aligned)
+}
+
+#[derive(Default)]
+/// The textures of the bitmap subtitles being shown, so they're only uploaded once.
+pub(crate) struct SubtitleTextures {
+    textures: Vec<(Arc<ColorImage>, TextureHandle)>,
+}
+
+/// Draw the given cues over the video at `rect`, scaled with its size. `video_size` is the native size of
+/// the video, which bitmap subtitles without a canvas size of their own are positioned relative to.
+pub(crate) fn paint_subtitles(
+    ui: &Ui,
+    rect: Rect,
+    subtitles: &[Subtitle],
+    video_size: [u32; 2],
+    textures: &mut SubtitleTextures,
+) {
+    let bitmaps = subtitles
+        .iter()
+        .filter_map(|s| match &s.content {
+            SubtitleContent::Bitmap(bitmap) => Some(bitmap),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    textures
+        .textures
+        .retain(|(image, _)| bitmaps.iter().any(|b| Arc::ptr_eq(image, &b.image)));
+    for bitmap in bitmaps {
+        let texture = match textures
+            .textures
+            .iter()
+            .find(|(image, _)| Arc::ptr_eq(image, &bitmap.image))
+        {
+            Some((_, texture)) => texture.clone(),
+            None => {
+                let texture = ui.ctx().load_texture(
+                    "subtitle",
+                    (*bitmap.image).clone(),
+                    TextureOptions::LINEAR,
+                );
+                textures
+                    .textures
+                    .push((Arc::clone(&bitmap.image), texture.clone()));
+                texture
+            }
+        };
+        let [canvas_width, canvas_height] = bitmap.canvas_size.unwrap_or(video_size);
+        let scale = vec2(
+            rect.width() / canvas_width.max(1) as f32,
+            rect.height() / canvas_height.max(1) as f32,
+        );
+        let bitmap_rect = Rect::from_min_size(
+            rect.min + bitmap.rect.min.to_vec2() * scale,
+            bitmap.rect.size() * scale,
+        );
+        ui.painter().image(
+            texture.id(),
+            bitmap_rect,
+            Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            Color32::WHITE,
+        );
+    }
+
+    let font_size = (rect.height() * 0.05).clamp(12., 48.);
+    let margin = rect.height() * 0.08;
+    for top_aligned in [false, true] {
+        let mut job = LayoutJob::default();
+        job.wrap.max_width = rect.width() * 0.9;
+        job.halign = egui::Align::Center;
+        for spans in subtitles.iter().filter_map(|s| match &s.content {
+            SubtitleContent::Text {
+                spans,
+                top_aligned: subtitle_top_aligned,
+            } if *subtitle_top_aligned == top_aligned => Some(spans),
+            _ => None,
+        }) {
+            if !job.text.is_empty() {
+                job.append("\n", 0., TextFormat::default());
+            }
+            for span in spans.iter() {
+                let format = TextFormat {
+                    font_id: FontId::proportional(font_size),
+                    // egui's default fonts have no bold face, so bold text is brighter instead
+                    color: if span.bold {
+                        Color32::WHITE
+                    } else {
+                        Color32::from_gray(220)
+                    },
+                    italics: span.italic,
+                    underline: if span.underline {
+                        Stroke::new(1., Color32::WHITE)
+                    } else {
+                        Stroke::NONE
+                    },
+                    ..Default::default()
+                };
+                job.append(&span.text, 0., format);
+            }
+        }
+        if job.text.is_empty() {
+            continue;
+        }
+        let galley = ui.fonts(|f| f.layout_job(job));
+        let anchor = if top_aligned {
+            rect.center_top() + vec2(0., margin)
+        } else {
+            rect.center_bottom() - vec2(0., margin + galley.size().y)
+        };
+        // the galley is centered around its anchor because of `halign`
+        let background_rect = galley.rect.translate(anchor.to_vec2()).expand(4.);
+        ui.painter().rect_filled(
+            background_rect,
+            Rounding::same(3.),
+            Color32::from_black_alpha(160),
+        );
+        ui.painter().galley(anchor, galley);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MediaSource;
+    use std::time::Instant;
+
+    const SRT: &str = "1
+00:00:01,000 --> 00:00:02,000
+One
+
+2
+00:00:02,500 --> 00:00:04,000
+Two
+
+3
+00:00:03,000 --> 00:00:03,500
+Three
+";
+
+    /// A streamer of `SRT`, once it has decoded every cue.
+    fn srt_streamer() -> SubtitleStreamer {
+        let input = Arc::<[u8]>::from(SRT.as_bytes()).open().unwrap();
+        let streamer = SubtitleStreamer::spawn(input, None, EventSenders::default()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while streamer.subtitles.lock().len() < 3 {
+            assert!(Instant::now() < deadline, "the cues were never decoded");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        streamer
+    }
+
+    /// The text of the cues shown at `position_ms`.
+    fn texts_at(streamer: &SubtitleStreamer, position_ms: i64) -> Vec<String> {
+        streamer
+            .subtitles_at(position_ms)
+            .into_iter()
+            .map(|subtitle| match subtitle.content {
+                SubtitleContent::Text { spans, .. } => {
+                    spans.into_iter().map(|span| span.text).collect()
+                }
+                SubtitleContent::Bitmap(_) => panic!("a bitmap cue from text subtitles"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cues_are_shown_from_their_start_until_their_end() {
+        let streamer = srt_streamer();
+        assert!(texts_at(&streamer, 0).is_empty());
+        assert!(texts_at(&streamer, 999).is_empty());
+        assert_eq!(texts_at(&streamer, 1000), ["One"]);
+        assert_eq!(texts_at(&streamer, 1999), ["One"]);
+        assert!(texts_at(&streamer, 2000).is_empty());
+        assert_eq!(texts_at(&streamer, 2500), ["Two"]);
+        assert!(texts_at(&streamer, 4000).is_empty());
+    }
+
+    #[test]
+    fn overlapping_cues_are_shown_together() {
+        let streamer = srt_streamer();
+        assert_eq!(texts_at(&streamer, 3000), ["Two", "Three"]);
+        assert_eq!(texts_at(&streamer, 3499), ["Two", "Three"]);
+        assert_eq!(texts_at(&streamer, 3500), ["Two"]);
+    }
+
+    #[test]
+    fn bitmaps_are_converted_through_their_palette() {
+        // opaque red, half transparent green
+        let palette: [u32; 2] = [0xffff0000, 0x8000ff00];
+        // 3x2, in rows of 4 bytes, the last of which is padding
+        let indices: [u8; 8] = [0, 1, 0, 9, 1, 1, 0, 9];
+        let image_and_rect = unsafe {
+            let mut rect: ffmpeg::ffi::AVSubtitleRect = std::mem::zeroed();
+            rect.x = 10;
+            rect.y = 20;
+            rect.w = 3;
+            rect.h = 2;
+            rect.nb_colors = 2;
+            rect.data[0] = indices.as_ptr() as *mut u8;
+            rect.data[1] = palette.as_ptr() as *mut u8;
+            rect.linesize[0] = 4;
+            bitmap_to_image(&Bitmap::wrap(&rect))
+        };
+        let (image, rect) = image_and_rect.unwrap();
+        let red = Color32::from_rgba_unmultiplied(255, 0, 0, 255);
+        let green = Color32::from_rgba_unmultiplied(0, 255, 0, 128);
+        assert_eq!(image.size, [3, 2]);
+        assert_eq!(image.pixels, [red, green, red, green, green, red]);
+        assert_eq!(rect, Rect::from_min_size(pos2(10., 20.), vec2(3., 2.)));
+    }
+
+    #[test]
+    fn empty_bitmaps_are_skipped() {
+        let palette: [u32; 1] = [0xffffffff];
+        let indices: [u8; 1] = [0];
+        unsafe {
+            let mut rect: ffmpeg::ffi::AVSubtitleRect = std::mem::zeroed();
+            rect.w = 0;
+            rect.h = 1;
+            rect.nb_colors = 1;
+            rect.data[0] = indices.as_ptr() as *mut u8;
+            rect.data[1] = palette.as_ptr() as *mut u8;
+            rect.linesize[0] = 1;
+            assert!(bitmap_to_image(&Bitmap::wrap(&rect)).is_none());
+            // without pixels
+            rect.w = 1;
+            rect.data[0] = std::ptr::null_mut();
+            assert!(bitmap_to_image(&Bitmap::wrap(&rect)).is_none());
+        }
+    }
+
+    #[test]
+    fn indices_past_the_palette_are_transparent() {
+        let palette: [u32; 1] = [0xffffffff];
+        let indices: [u8; 2] = [0, 5];
+        let image_and_rect = unsafe {
+            let mut rect: ffmpeg::ffi::AVSubtitleRect = std::mem::zeroed();
+            rect.w = 2;
+            rect.h = 1;
+            rect.nb_colors = 1;
+            rect.data[0] = indices.as_ptr() as *mut u8;
+            rect.data[1] = palette.as_ptr() as *mut u8;
+            rect.linesize[0] = 2;
+            bitmap_to_image(&Bitmap::wrap(&rect))
+        };
+        let (image, _) = image_and_rect.unwrap();
+        assert_eq!(image.pixels, [Color32::WHITE, Color32::TRANSPARENT]);
+    }
+
+    fn span(text: &str) -> SubtitleSpan {
+        SubtitleSpan {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dialogue_text_is_the_last_field() {
+        let (spans, top_aligned) = parse_ass_dialogue("0,0,Default,,0,0,0,,Hello");
+        assert_eq!(spans, vec![span("Hello")]);
+        assert!(!top_aligned);
+    }
+
+    #[test]
+    fn dialogue_with_prefix_has_an_extra_field() {
+        let (spans, _) =
+            parse_ass_dialogue("Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hello");
+        assert_eq!(spans, vec![span("Hello")]);
+    }
+
+    #[test]
+    fn commas_in_the_text_are_kept() {
+        let (spans, _) = parse_ass_dialogue("0,0,Default,,0,0,0,,Well, hello, there");
+        assert_eq!(spans, vec![span("Well, hello, there")]);
+        let (spans, _) =
+            parse_ass_dialogue("Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Well, hello");
+        assert_eq!(spans, vec![span("Well, hello")]);
+    }
+
+    #[test]
+    fn line_breaks() {
+        let (spans, _) = parse_ass_dialogue("0,0,Default,,0,0,0,,One\\NTwo\\nThree\\hFour");
+        assert_eq!(spans, vec![span("One\nTwo\nThree Four")]);
+    }
+
+    #[test]
+    fn italic_until_reset() {
+        let (spans, _) = parse_ass_dialogue("0,0,Default,,0,0,0,,{\\i1}leaning{\\r} upright");
+        assert_eq!(
+            spans,
+            vec![
+                SubtitleSpan {
+                    italic: true,
+                    ..span("leaning")
+                },
+                span(" upright"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_clears_every_style() {
+        let (spans, _) = parse_ass_dialogue("0,0,Default,,0,0,0,,{\\b1\\u1}loud{\\r}quiet");
+        assert_eq!(
+            spans,
+            vec![
+                SubtitleSpan {
+                    bold: true,
+                    underline: true,
+                    ..span("loud")
+                },
+                span("quiet"),
+            ]
+        );
+    }
+
+    #[test]
+    fn top_alignment() {
+        let (spans, top_aligned) = parse_ass_dialogue("0,0,Default,,0,0,0,,{\\an8}Up here");
+        assert_eq!(spans, vec![span("Up here")]);
+        assert!(top_aligned);
+    }
+}
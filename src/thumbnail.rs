@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::sync::Arc;
+
+use chrono::Duration;
+use egui::{TextureHandle, TextureOptions};
+use parking_lot::Mutex;
+
+use crate::extractor::FrameExtractor;
+use crate::io::MediaSource;
+
+/// How many evenly spaced thumbnails the seekbar preview snaps to.
+const THUMBNAIL_COUNT: usize = 100;
+
+/// Decodes seekbar preview thumbnails on a background thread, and keeps the ones already decoded.
+pub(crate) struct ThumbnailCache {
+    thumbnails: Arc<Mutex<HashMap<usize, TextureHandle>>>,
+    /// Requested thumbnails that are decoded or being decoded. Ones that fail to decode are taken out again,
+    /// so they're retried when next requested.
+    requested: Arc<Mutex<HashSet<usize>>>,
+    request_sender: Sender<usize>,
+}
+
+impl ThumbnailCache {
+    /// Start decoding thumbnails `thumbnail_width` pixels wide from the video stream `stream_index` of
+    /// `media_source`. Requested thumbnails are decoded first, and the rest in order in between.
+    pub(crate) fn new(
+        ctx: &egui::Context,
+        media_source: Arc<dyn MediaSource>,
+        stream_index: usize,
+        duration_ms: i64,
+        thumbnail_width: u32,
+    ) -> Self {
+        let thumbnails = Arc::new(Mutex::new(HashMap::new()));
+        let requested = Arc::new(Mutex::new(HashSet::new()));
+        let (request_sender, request_receiver) = mpsc::channel::<usize>();
+        let thread_thumbnails = Arc::clone(&thumbnails);
+        let thread_requested = Arc::clone(&requested);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let Ok(mut frame_extractor) =
+                FrameExtractor::with_stream(move || media_source.open(), stream_index)
+            else {
+                return;
+            };
+            let mut pending = Vec::new();
+            let mut next_in_order = 0;
+            loop {
+                // stops once the cache (and with it the sender) is dropped
+                loop {
+                    match request_receiver.try_recv() {
+                        Ok(index) => pending.push(index),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+                // the pointer moves faster than thumbnails decode, so the latest request goes first. seeking
+                // forwards from one thumbnail to the next is cheap, so the rest are decoded in order
+                let index = match pending.pop() {
+                    Some(index) => index,
+                    None if next_in_order < THUMBNAIL_COUNT => {
+                        next_in_order += 1;
+                        next_in_order - 1
+                    }
+                    None => match request_receiver.recv() {
+                        Ok(index) => index,
+                        Err(_) => return,
+                    },
+                };
+                if thread_thumbnails.lock().contains_key(&index) {
+                    continue;
+                }
+                let time = Duration::milliseconds(thumbnail_time_ms(index, duration_ms));
+                match frame_extractor.frame_at(time, [thumbnail_width, 0]) {
+                    Ok(image) => {
+                        let texture = ctx.load_texture(
+                            format!("thumbnail_{index}"),
+                            image,
+                            TextureOptions::LINEAR,
+                        );
+                        thread_thumbnails.lock().insert(index, texture);
+                        ctx.request_repaint();
+                    }
+                    Err(_) => {
+                        thread_requested.lock().remove(&index);
+                    }
+                }
+            }
+        });
+        Self {
+            thumbnails,
+            requested,
+            request_sender,
+        }
+    }
+
+    /// The thumbnail closest to `seek_frac` (from `0` to `1`). Requests the exact thumbnail if it hasn't
+    /// been decoded yet, returning the closest decoded one meanwhile.
+    pub(crate) fn thumbnail_at(&mut self, seek_frac: f32) -> Option<TextureHandle> {
+        let index = (seek_frac.clamp(0., 1.) * (THUMBNAIL_COUNT - 1) as f32).round() as usize;
+        if self.requested.lock().insert(index) {
+            let _ = self.request_sender.send(index);
+        }
+        let thumbnails = self.thumbnails.lock();
+        thumbnails
+            .iter()
+            .min_by_key(|(other_index, _)| other_index.abs_diff(index))
+            .map(|(_, texture)| texture.clone())
+    }
+}
+
+fn thumbnail_time_ms(index: usize, duration_ms: i64) -> i64 {
+    (index as f64 / (THUMBNAIL_COUNT - 1) as f64 * duration_ms as f64) as i64
+}
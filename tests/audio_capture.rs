@@ -0,0 +1,147 @@
+use egui_video::audio::CaptureBackend;
+use egui_video::{Player, PlayerConfig};
+use std::time::{Duration, Instant};
+
+/// Has two audio tracks: a 440 Hz sine at half scale, and an 880 Hz one at a tenth.
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/two_video_two_audio_tracks.avi"
+);
+/// A 440 Hz sine at half scale, without any video or cover art.
+const AUDIO_ONLY_FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/sine_440hz_1s.wav"
+);
+const SAMPLE_RATE: u32 = 44100;
+
+/// Play the 440 Hz track of the fixture through a manual capture backend, pulling samples at the pace a
+/// device would until half a second of audio has been captured. Returns the interleaved samples, from the
+/// first that isn't silent.
+fn play_and_capture(sample_rate: u32, channels: u16) -> Vec<f32> {
+    let ctx = egui::Context::default();
+    let mut capture = CaptureBackend::manual(sample_rate, channels);
+    let mut player = Player::new(&ctx, FIXTURE, PlayerConfig::default())
+        .unwrap()
+        .with_audio(&mut capture)
+        .unwrap();
+    player.select_audio_track(2).unwrap();
+    capture_playback(&ctx, &mut player, &mut capture, sample_rate, channels)
+}
+
+/// Start `player` and capture half a second of its audio, from the first sample that isn't silent.
+fn capture_playback(
+    ctx: &egui::Context,
+    player: &mut Player,
+    capture: &mut CaptureBackend,
+    sample_rate: u32,
+    channels: u16,
+) -> Vec<f32> {
+    player.start();
+
+    // the ui keeps the player going
+    let wanted_samples = sample_rate as usize / 2 * channels as usize;
+    let mut samples = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while samples.len() < wanted_samples && Instant::now() < deadline {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                player.ui(ui, [320., 240.]);
+            });
+        });
+        let rendered = capture.render(sample_rate as usize / 100);
+        // the silence before playback starts doesn't count
+        if !samples.is_empty() || rendered.iter().any(|sample| sample.abs() > 1e-3) {
+            samples.extend(rendered);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(samples.len() >= wanted_samples, "playback never started");
+    assert_eq!(player.last_error(), None);
+    samples.truncate(wanted_samples);
+    samples
+}
+
+/// One channel of interleaved samples, without the fade in.
+fn steady_channel(samples: &[f32], sample_rate: u32, channels: u16, channel: usize) -> Vec<f32> {
+    samples
+        .iter()
+        .skip(sample_rate as usize / 10 * channels as usize + channel)
+        .step_by(channels as usize)
+        .copied()
+        .collect()
+}
+
+fn peak(samples: &[f32]) -> f32 {
+    samples
+        .iter()
+        .fold(0f32, |peak, sample| peak.max(sample.abs()))
+}
+
+/// Check that `samples` are a 440 Hz sine, which crosses zero 880 times a second.
+fn assert_440_hz(samples: &[f32], sample_rate: u32) {
+    let crossings = samples
+        .windows(2)
+        .filter(|pair| (pair[0] < 0.) != (pair[1] < 0.))
+        .count();
+    let expected_crossings = 880. * samples.len() as f32 / sample_rate as f32;
+    assert!(
+        (crossings as f32 - expected_crossings).abs() < expected_crossings * 0.1,
+        "{crossings} zero crossings, expected about {expected_crossings}"
+    );
+}
+
+#[test]
+fn plays_a_sine_through_the_capture_backend() {
+    let samples = play_and_capture(SAMPLE_RATE, 1);
+    let steady = steady_channel(&samples, SAMPLE_RATE, 1, 0);
+    let peak = peak(&steady);
+    // at the default volume of one half
+    assert!(peak > 0.2 && peak < 0.3, "peak of {peak}");
+    assert_440_hz(&steady, SAMPLE_RATE);
+}
+
+#[test]
+fn resamples_to_the_device_rate() {
+    let sample_rate = 48000;
+    let samples = play_and_capture(sample_rate, 1);
+    let steady = steady_channel(&samples, sample_rate, 1, 0);
+    assert!(peak(&steady) > 0.1, "peak of {}", peak(&steady));
+    assert_440_hz(&steady, sample_rate);
+}
+
+#[test]
+fn upmixes_to_the_device_channels() {
+    let sample_rate = 48000;
+    for channels in [2, 6, 8] {
+        let samples = play_and_capture(sample_rate, channels);
+        // mono goes to the front channels or the center, depending on the layout
+        let loudest = (0..channels as usize)
+            .map(|channel| steady_channel(&samples, sample_rate, channels, channel))
+            .max_by(|a, b| peak(a).total_cmp(&peak(b)))
+            .unwrap();
+        assert!(
+            peak(&loudest) > 0.1,
+            "peak of {} with {channels} channels",
+            peak(&loudest)
+        );
+        assert_440_hz(&loudest, sample_rate);
+    }
+}
+
+#[test]
+fn plays_audio_only_media() {
+    let ctx = egui::Context::default();
+    let mut capture = CaptureBackend::manual(SAMPLE_RATE, 1);
+    let mut player = Player::new(&ctx, AUDIO_ONLY_FIXTURE, PlayerConfig::default())
+        .unwrap()
+        .with_audio(&mut capture)
+        .unwrap();
+    assert!(player.is_audio_only());
+    assert_eq!(player.video_track(), None);
+    let samples = capture_playback(&ctx, &mut player, &mut capture, SAMPLE_RATE, 1);
+    let steady = steady_channel(&samples, SAMPLE_RATE, 1, 0);
+    let peak = peak(&steady);
+    // at the default volume of one half
+    assert!(peak > 0.2 && peak < 0.3, "peak of {peak}");
+    assert_440_hz(&steady, SAMPLE_RATE);
+}
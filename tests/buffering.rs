@@ -0,0 +1,176 @@
+use egui_video::io::UrlSource;
+use egui_video::{Player, PlayerConfig, PlayerEvent, PlayerState};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Answer http requests on a local port until the test exits, with `respond` writing the body for the
+/// requested path. Returns the url of the server.
+fn serve_with(respond: impl Fn(&str, &mut TcpStream) + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // only the path of the request matters, but all of it is read before answering
+            let mut reader = BufReader::new(&mut stream);
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                line.clear();
+            }
+            let path = request_line.split(' ').nth(1).unwrap_or("/").to_string();
+            respond(&path, &mut stream);
+        }
+    });
+    url
+}
+
+/// Write `body` as the answer to a request, stopping for `stall_for` once `stall_at` bytes of it have been
+/// sent, as a slow connection would.
+fn respond_stalling(stream: &mut TcpStream, body: &[u8], stall_at: usize, stall_for: Duration) {
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let _ = stream.write_all(header.as_bytes());
+    let (before_stall, after_stall) = body.split_at(stall_at);
+    let _ = stream.write_all(before_stall);
+    let _ = stream.flush();
+    std::thread::sleep(stall_for);
+    let _ = stream.write_all(after_stall);
+}
+
+/// Serve `body` over http, returning its url.
+fn serve(body: &'static [u8]) -> String {
+    serve_stalling(body, body.len(), Duration::ZERO)
+}
+
+/// Like [`serve`], but stall for `stall_for` once `stall_at` bytes of the body have been sent.
+fn serve_stalling(body: &'static [u8], stall_at: usize, stall_for: Duration) -> String {
+    let url = serve_with(move |_, stream| respond_stalling(stream, body, stall_at, stall_for));
+    format!("{url}/media")
+}
+
+/// A 32x24 video at 25 fps of `frames` flat gray frames, each brighter than the last, and the size of its
+/// header and of each frame.
+fn gray_ramp_y4m(frames: usize) -> (Vec<u8>, usize, usize) {
+    let mut video = b"YUV4MPEG2 W32 H24 F25:1 Ip A1:1 C420\n".to_vec();
+    let header_len = video.len();
+    for i in 0..frames {
+        video.extend_from_slice(b"FRAME\n");
+        video.extend(std::iter::repeat((16 + i * 4) as u8).take(32 * 24));
+        video.extend(std::iter::repeat(128).take(32 * 24 / 2));
+    }
+    let frame_len = (video.len() - header_len) / frames;
+    (video, header_len, frame_len)
+}
+
+#[test]
+fn buffered_until_reports_the_read_position() {
+    let (video, _, _) = gray_ramp_y4m(50);
+    let ctx = egui::Context::default();
+    let mut player = Player::from_source(
+        &ctx,
+        UrlSource::new(serve(video.leak())),
+        PlayerConfig::default(),
+    )
+    .unwrap();
+    player.start();
+
+    // nothing is presented without a ui, so only the read ahead moves the buffered position
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while player.buffered_until_ms() == 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let buffered_until_ms = player.buffered_until_ms();
+    assert!(buffered_until_ms > 0, "nothing was read ahead");
+    assert!(
+        buffered_until_ms <= 2000,
+        "read past the end: {buffered_until_ms} ms"
+    );
+}
+
+#[test]
+fn buffers_while_the_connection_stalls() {
+    let (video, header_len, frame_len) = gray_ramp_y4m(50);
+    // opening reads the first 20 frames to find the frame rate, so the stall comes after those
+    let stall_at = header_len + 30 * frame_len;
+    let url = serve_stalling(video.leak(), stall_at, Duration::from_millis(1500));
+    let ctx = egui::Context::default();
+    let mut player =
+        Player::from_source(&ctx, UrlSource::new(url), PlayerConfig::default()).unwrap();
+    let events = player.events();
+    player.start();
+
+    let mut received = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !received.contains(&PlayerEvent::EndOfFile) && Instant::now() < deadline {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                player.ui(ui, [320., 240.]);
+            });
+        });
+        received.extend(events.try_iter());
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(player.last_error(), None);
+    let position = |event: PlayerEvent| {
+        received
+            .iter()
+            .position(|received| *received == event)
+            .unwrap_or_else(|| panic!("{event:?} not in {received:?}"))
+    };
+    let underrun = position(PlayerEvent::BufferUnderrun);
+    let buffering = position(PlayerEvent::StateChanged(PlayerState::Buffering));
+    let end_of_file = position(PlayerEvent::EndOfFile);
+    // playback picks up again once the stall is over, and plays to the end
+    let resumed = received[buffering..]
+        .iter()
+        .position(|event| *event == PlayerEvent::StateChanged(PlayerState::Playing))
+        .map(|resumed| buffering + resumed)
+        .unwrap_or_else(|| panic!("playback never resumed: {received:?}"));
+    assert!(underrun <= buffering && buffering < resumed && resumed < end_of_file);
+}
+
+#[test]
+fn plays_an_hls_playlist() {
+    // the video is split in two segments between frames, which the hls demuxer reads back to back
+    let (video, header_len, frame_len) = gray_ramp_y4m(50);
+    let (first_segment, second_segment) = video.leak().split_at(header_len + 25 * frame_len);
+    let playlist = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:0\n\
+        #EXTINF:1.0,\nfirst.y4m\n#EXTINF:1.0,\nsecond.y4m\n#EXT-X-ENDLIST\n";
+    let url = serve_with(move |path, stream| {
+        let body = match path {
+            "/playlist.m3u8" => playlist.as_bytes(),
+            "/first.y4m" => first_segment,
+            "/second.y4m" => second_segment,
+            _ => &[],
+        };
+        respond_stalling(stream, body, body.len(), Duration::ZERO);
+    });
+    let ctx = egui::Context::default();
+    let mut player = Player::from_source(
+        &ctx,
+        UrlSource::new(format!("{url}/playlist.m3u8")),
+        PlayerConfig::default(),
+    )
+    .unwrap();
+    player.start();
+
+    // playing the first segment makes room to read the second
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while player.buffered_until_ms() <= 1000 && Instant::now() < deadline {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                player.ui(ui, [320., 240.]);
+            });
+        });
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(
+        player.buffered_until_ms() > 1000,
+        "the second segment was never read"
+    );
+    assert_eq!(player.last_error(), None);
+}
@@ -0,0 +1,51 @@
+use egui_video::{Error, Player, PlayerConfig};
+
+const VIDEO_FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/gray_ramp_10fps_2s.y4m"
+);
+const TRACKS_FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/two_video_two_audio_tracks.avi"
+);
+const AUDIO_FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/sine_440hz_1s.wav"
+);
+
+#[test]
+fn opening_a_missing_file_fails() {
+    let ctx = egui::Context::default();
+    let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/missing.y4m");
+    assert!(matches!(
+        Player::new(&ctx, missing, PlayerConfig::default()),
+        Err(Error::Open(_))
+    ));
+}
+
+#[test]
+fn selecting_a_missing_track_fails() {
+    let ctx = egui::Context::default();
+    let mut player = Player::new(&ctx, VIDEO_FIXTURE, PlayerConfig::default()).unwrap();
+    assert_eq!(player.select_video_track(5), Err(Error::InvalidTrack(5)));
+    // the video stream isn't an audio track
+    assert_eq!(player.select_audio_track(0), Err(Error::InvalidTrack(0)));
+}
+
+#[test]
+fn audio_tracks_need_audio_to_be_set_up() {
+    let ctx = egui::Context::default();
+    let mut player = Player::new(&ctx, TRACKS_FIXTURE, PlayerConfig::default()).unwrap();
+    assert!(matches!(
+        player.select_audio_track(2),
+        Err(Error::Unavailable(_))
+    ));
+}
+
+#[test]
+fn audio_without_cover_art_has_no_frame() {
+    let ctx = egui::Context::default();
+    let mut player = Player::new(&ctx, AUDIO_FIXTURE, PlayerConfig::default()).unwrap();
+    assert!(player.is_audio_only());
+    assert!(matches!(player.current_frame(), Err(Error::Unavailable(_))));
+}
@@ -0,0 +1,77 @@
+use chrono::Duration;
+use egui::ColorImage;
+use egui_video::FrameExtractor;
+use std::path::PathBuf;
+
+/// 32x24 at 10 fps for 2 s. Every frame is a flat gray, 10 luma steps brighter than the frame before.
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/gray_ramp_10fps_2s.y4m"
+);
+const FRAME_MS: i64 = 100;
+
+fn extractor() -> FrameExtractor {
+    FrameExtractor::new(PathBuf::from(FIXTURE)).unwrap()
+}
+
+/// Which frame of the fixture `image` shows, from its brightness.
+fn frame_index(image: &ColorImage) -> usize {
+    // 10 steps of limited range luma are 10 * 255 / 219 steps of rgb
+    (image.pixels[0].r() as f32 * 219. / 2550.).round() as usize
+}
+
+#[test]
+fn reads_the_duration_and_size() {
+    let extractor = extractor();
+    assert_eq!(extractor.duration(), Duration::milliseconds(2000));
+    assert_eq!(extractor.native_size(), [32, 24]);
+}
+
+#[test]
+fn extracts_the_frame_at_each_timestamp() {
+    let mut extractor = extractor();
+    // out of order, so each extraction seeks backwards or forwards
+    for index in [0, 5, 19, 10, 1, 18] {
+        let image = extractor
+            .frame_at(Duration::milliseconds(index as i64 * FRAME_MS), [0, 0])
+            .unwrap();
+        assert_eq!(frame_index(&image), index);
+    }
+}
+
+#[test]
+fn extracts_the_last_frame_past_the_end() {
+    let mut extractor = extractor();
+    let image = extractor
+        .frame_at(Duration::milliseconds(5000), [0, 0])
+        .unwrap();
+    assert_eq!(frame_index(&image), 19);
+}
+
+#[test]
+fn extracts_evenly_spaced_frames() {
+    let mut extractor = extractor();
+    let images = extractor.evenly_spaced_frames(4, [0, 0]).unwrap();
+    // the middles of four 500 ms parts, at 250, 750, 1250 and 1750 ms
+    let indices = images.iter().map(frame_index).collect::<Vec<_>>();
+    assert_eq!(indices, [3, 8, 13, 18]);
+}
+
+#[test]
+fn scales_frames_to_the_output_size() {
+    let mut extractor = extractor();
+    let time = Duration::milliseconds(500);
+    for (size, expected_size) in [
+        ([0, 0], [32, 24]),
+        ([16, 0], [16, 12]),
+        ([0, 48], [64, 48]),
+        ([10, 10], [10, 10]),
+    ] {
+        let image = extractor.frame_at(time, size).unwrap();
+        assert_eq!(
+            image.size, expected_size,
+            "for a requested size of {size:?}"
+        );
+        assert_eq!(frame_index(&image), 5);
+    }
+}
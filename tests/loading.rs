@@ -0,0 +1,63 @@
+use egui_video::io::{MediaInput, UrlSource};
+use egui_video::{Error, Player, PlayerConfig, PlayerLoader};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/gray_ramp_10fps_2s.y4m"
+);
+
+/// Poll `loader` until it's done, or fail after a while.
+fn wait_for(loader: &mut PlayerLoader) -> Result<Player, Error> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if let Some(result) = loader.poll() {
+            return result;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("still loading");
+}
+
+#[test]
+fn loads_in_the_background() {
+    let ctx = egui::Context::default();
+    let mut loader = Player::open_async(&ctx, FIXTURE, PlayerConfig::default());
+    let player = wait_for(&mut loader).unwrap();
+    assert_eq!([player.width, player.height], [32, 24]);
+    assert!(!loader.is_loading());
+    assert_eq!(loader.error(), None);
+    // the player is only handed out once
+    assert!(loader.poll().is_none());
+}
+
+#[test]
+fn reports_why_loading_failed() {
+    let ctx = egui::Context::default();
+    let mut loader = Player::open_async(&ctx, "missing.mp4", PlayerConfig::default());
+    assert!(matches!(wait_for(&mut loader), Err(Error::Open(_))));
+    assert!(matches!(loader.error(), Some(Error::Open(_))));
+}
+
+#[test]
+fn gives_up_on_a_server_that_never_answers() {
+    // connections are accepted by the os, but never answered
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/media", listener.local_addr().unwrap());
+    let ctx = egui::Context::default();
+    let mut loader = Player::open_source_async(&ctx, UrlSource::new(url), PlayerConfig::default())
+        .with_timeout(Duration::from_millis(200));
+    let started_at = Instant::now();
+    assert_eq!(wait_for(&mut loader).err(), Some(Error::Timeout));
+    assert!(started_at.elapsed() < Duration::from_secs(2));
+    assert_eq!(loader.error(), Some(&Error::Timeout));
+}
+
+#[test]
+fn reports_a_panicked_loader() {
+    let ctx = egui::Context::default();
+    let media_source = || -> Result<MediaInput, Error> { panic!("the source panicked") };
+    let mut loader = Player::open_source_async(&ctx, media_source, PlayerConfig::default());
+    assert_eq!(wait_for(&mut loader).err(), Some(Error::LoaderPanicked));
+}
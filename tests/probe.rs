@@ -0,0 +1,68 @@
+use egui_video::probe::StreamDetails;
+use egui_video::MediaInfo;
+use ffmpeg_next::media::Type;
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn probes_a_video() {
+    let media_info = MediaInfo::probe(fixture("gray_ramp_10fps_2s.y4m")).unwrap();
+    assert_eq!(media_info.duration_ms, 2000);
+    assert_eq!(media_info.container, "yuv4mpegpipe");
+    assert_eq!(media_info.streams.len(), 1);
+    assert!(media_info.audio_stream().is_none());
+
+    let video_stream = media_info.video_stream().unwrap();
+    assert_eq!(video_stream.index, 0);
+    assert_eq!(video_stream.codec, "rawvideo");
+    assert_eq!(video_stream.duration_ms, Some(2000));
+    assert_eq!(
+        video_stream.details,
+        StreamDetails::Video {
+            width: 32,
+            height: 24,
+            frame_rate: 10.,
+            pixel_format: Some("yuv420p".to_string()),
+            rotation: 0.,
+        }
+    );
+}
+
+#[test]
+fn probes_audio() {
+    let media_info = MediaInfo::probe(fixture("sine_440hz_1s.wav")).unwrap();
+    assert_eq!(media_info.duration_ms, 1000);
+    assert_eq!(media_info.container, "wav");
+    assert!(media_info.video_stream().is_none());
+    assert!(media_info.chapters.is_empty());
+
+    let audio_stream = media_info.audio_stream().unwrap();
+    assert_eq!(audio_stream.medium, Type::Audio);
+    assert_eq!(audio_stream.codec, "pcm_s16le");
+    // 16 bit mono at 44.1 kHz
+    assert_eq!(audio_stream.bit_rate, 705_600);
+    let StreamDetails::Audio {
+        sample_rate,
+        channels,
+        ref sample_format,
+        ..
+    } = audio_stream.details
+    else {
+        panic!("not audio: {:?}", audio_stream.details);
+    };
+    assert_eq!((sample_rate, channels), (44100, 1));
+    assert_eq!(sample_format.as_deref(), Some("s16"));
+}
+
+#[test]
+fn missing_media_fails_to_open() {
+    assert!(matches!(
+        MediaInfo::probe(fixture("missing.mp4")),
+        Err(egui_video::Error::Open(_))
+    ));
+}
@@ -0,0 +1,108 @@
+use chrono::Duration;
+use egui::{ColorImage, ImageData};
+use egui_video::{Player, PlayerConfig, SeekMode};
+use std::time::Instant;
+
+/// 32x24 at 10 fps for 2 s. Every frame is a flat gray, 10 luma steps brighter than the frame before.
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/gray_ramp_10fps_2s.y4m"
+);
+
+/// Which frame of the fixture `image` shows, from its brightness.
+fn frame_index(image: &ColorImage) -> usize {
+    (image.pixels[0].r() as f32 * 219. / 2550.).round() as usize
+}
+
+/// Run the ui until the frame `index` is shown, or fail after a while. The shown frame is the last one the
+/// player uploaded to its texture.
+fn wait_for_frame(ctx: &egui::Context, player: &mut Player, index: usize) {
+    let deadline = Instant::now() + std::time::Duration::from_secs(5);
+    let mut shown_index = None;
+    while Instant::now() < deadline {
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                player.ui(ui, [320., 240.]);
+            });
+        });
+        for (_, delta) in &output.textures_delta.set {
+            if let ImageData::Color(image) = &delta.image {
+                if image.size == [32, 24] {
+                    shown_index = Some(frame_index(image));
+                }
+            }
+        }
+        if shown_index == Some(index) {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    panic!("frame {index} was never shown, the last was {shown_index:?}");
+}
+
+fn paused_at_frame(ctx: &egui::Context, index: usize) -> Player {
+    let mut player = Player::new(ctx, FIXTURE, PlayerConfig::default()).unwrap();
+    player
+        .seek_to(Duration::milliseconds(index as i64 * 100))
+        .unwrap();
+    wait_for_frame(ctx, &mut player, index);
+    player
+}
+
+#[test]
+fn steps_backward_a_frame_at_a_time() {
+    let ctx = egui::Context::default();
+    let mut player = paused_at_frame(&ctx, 10);
+    player.step_backward().unwrap();
+    wait_for_frame(&ctx, &mut player, 9);
+    player.step_backward().unwrap();
+    wait_for_frame(&ctx, &mut player, 8);
+    assert_eq!(player.last_error(), None);
+}
+
+#[test]
+fn quick_backward_steps_are_not_lost() {
+    let ctx = egui::Context::default();
+    let mut player = paused_at_frame(&ctx, 10);
+    // the second step comes before the frame of the first has been shown
+    for _ in 0..3 {
+        player.step_backward().unwrap();
+    }
+    wait_for_frame(&ctx, &mut player, 7);
+    assert_eq!(player.last_error(), None);
+}
+
+#[test]
+fn quick_forward_steps_are_not_lost() {
+    let ctx = egui::Context::default();
+    let mut player = paused_at_frame(&ctx, 10);
+    for _ in 0..3 {
+        player.step_forward().unwrap();
+    }
+    wait_for_frame(&ctx, &mut player, 13);
+    assert_eq!(player.last_error(), None);
+}
+
+#[test]
+fn accurate_seeks_go_to_the_first_frame_from_the_target() {
+    let ctx = egui::Context::default();
+    let mut player = paused_at_frame(&ctx, 5);
+    player.seek_to(Duration::milliseconds(1040)).unwrap();
+    wait_for_frame(&ctx, &mut player, 11);
+    player.seek_to(Duration::milliseconds(300)).unwrap();
+    wait_for_frame(&ctx, &mut player, 3);
+    assert_eq!(player.last_error(), None);
+}
+
+#[test]
+fn keyframe_seeks_snap_to_the_nearest_keyframe() {
+    let ctx = egui::Context::default();
+    let mut player = paused_at_frame(&ctx, 5);
+    player.set_seek_mode(SeekMode::Keyframe);
+    // every frame of the fixture is a keyframe
+    player.seek_to(Duration::milliseconds(1040)).unwrap();
+    wait_for_frame(&ctx, &mut player, 10);
+    player.seek_to(Duration::milliseconds(1060)).unwrap();
+    wait_for_frame(&ctx, &mut player, 11);
+    assert_eq!(player.last_error(), None);
+}
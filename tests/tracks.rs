@@ -0,0 +1,157 @@
+use chrono::Duration;
+use egui::{ColorImage, ImageData};
+use egui_video::audio::CaptureBackend;
+use egui_video::{Player, PlayerConfig};
+use std::time::Instant;
+
+/// 16x12 at 10 fps for 1 s, with two video and two audio tracks. The frames of the first video track are
+/// a flat gray of 10 times their index, those of the second 150 brighter. The first audio track is a
+/// 440 Hz sine at half scale, the second an 880 Hz one at a tenth.
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/two_video_two_audio_tracks.avi"
+);
+const SAMPLE_RATE: u32 = 8000;
+
+/// Which video track and frame of the fixture `image` shows, from its brightness.
+fn track_and_frame(image: &ColorImage) -> (usize, usize) {
+    let value = image.pixels[0].r() as usize;
+    if value >= 150 {
+        (1, (value - 150) / 10)
+    } else {
+        (0, value / 10)
+    }
+}
+
+/// Run the ui until `track` shows the frame `index`, or fail after a while. The shown frame is the last one
+/// the player uploaded to its texture.
+fn wait_for_frame(ctx: &egui::Context, player: &mut Player, track: usize, index: usize) {
+    let deadline = Instant::now() + std::time::Duration::from_secs(5);
+    let mut shown = None;
+    while Instant::now() < deadline {
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                player.ui(ui, [320., 240.]);
+            });
+        });
+        for (_, delta) in &output.textures_delta.set {
+            if let ImageData::Color(image) = &delta.image {
+                if image.size == [16, 12] {
+                    shown = Some(track_and_frame(image));
+                }
+            }
+        }
+        if shown == Some((track, index)) {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    panic!("frame {index} of track {track} was never shown, the last was {shown:?}");
+}
+
+#[test]
+fn lists_the_tracks() {
+    let ctx = egui::Context::default();
+    let player = Player::new(&ctx, FIXTURE, PlayerConfig::default()).unwrap();
+    let stream_indices = |tracks: &[egui_video::TrackInfo]| {
+        tracks
+            .iter()
+            .map(|track| track.stream_index)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(stream_indices(player.video_tracks()), [0, 1]);
+    assert_eq!(stream_indices(player.audio_tracks()), [2, 3]);
+    assert!(player
+        .video_tracks()
+        .iter()
+        .all(|track| track.codec == "rawvideo"));
+    assert!(player
+        .audio_tracks()
+        .iter()
+        .all(|track| track.codec == "pcm_s16le"));
+    assert!(matches!(player.video_track(), Some(0 | 1)));
+    // no audio is played until it's set up
+    assert_eq!(player.audio_track(), None);
+}
+
+#[test]
+fn switches_video_tracks_at_the_current_position() {
+    let ctx = egui::Context::default();
+    let mut player = Player::new(&ctx, FIXTURE, PlayerConfig::default()).unwrap();
+    let track = player.video_track().unwrap();
+    player.seek_to(Duration::milliseconds(500)).unwrap();
+    wait_for_frame(&ctx, &mut player, track, 5);
+
+    let other_track = 1 - track;
+    player.select_video_track(other_track).unwrap();
+    assert_eq!(player.video_track(), Some(other_track));
+    wait_for_frame(&ctx, &mut player, other_track, 5);
+    // and back
+    player.select_video_track(track).unwrap();
+    wait_for_frame(&ctx, &mut player, track, 5);
+    assert_eq!(player.last_error(), None);
+}
+
+#[test]
+fn switches_audio_tracks_while_playing() {
+    let ctx = egui::Context::default();
+    let mut capture = CaptureBackend::manual(SAMPLE_RATE, 1);
+    let mut player = Player::new(&ctx, FIXTURE, PlayerConfig::default())
+        .unwrap()
+        .with_audio(&mut capture)
+        .unwrap();
+    let other_track = match player.audio_track() {
+        Some(2) => 3,
+        Some(3) => 2,
+        track => panic!("playing audio track {track:?}"),
+    };
+    player.start();
+
+    // pull samples at the pace a device would, switching tracks 200 ms into the audio
+    let switch_at = SAMPLE_RATE as usize / 5;
+    let wanted_samples = switch_at + SAMPLE_RATE as usize * 9 / 20;
+    let mut samples = Vec::new();
+    let deadline = Instant::now() + std::time::Duration::from_secs(5);
+    while samples.len() < wanted_samples && Instant::now() < deadline {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                player.ui(ui, [320., 240.]);
+            });
+        });
+        let rendered = capture.render(SAMPLE_RATE as usize / 100);
+        // the silence before playback starts doesn't count
+        if !samples.is_empty() || rendered.iter().any(|sample| sample.abs() > 1e-3) {
+            let switching = samples.len() < switch_at;
+            samples.extend(rendered);
+            if switching && samples.len() >= switch_at {
+                player.select_audio_track(other_track).unwrap();
+                assert_eq!(player.audio_track(), Some(other_track));
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(samples.len() >= wanted_samples, "playback never started");
+    assert_eq!(player.last_error(), None);
+
+    // leave time for the switch
+    let switched = &samples[switch_at + SAMPLE_RATE as usize / 4..wanted_samples];
+    let peak = switched
+        .iter()
+        .fold(0f32, |peak, sample| peak.max(sample.abs()));
+    let crossings = switched
+        .windows(2)
+        .filter(|pair| (pair[0] < 0.) != (pair[1] < 0.))
+        .count();
+    // at the default volume of one half
+    let (frequency, (min_peak, max_peak)) = if other_track == 2 {
+        (440., (0.2, 0.3))
+    } else {
+        (880., (0.03, 0.07))
+    };
+    assert!(peak > min_peak && peak < max_peak, "peak of {peak}");
+    let expected_crossings = 2. * frequency * switched.len() as f32 / SAMPLE_RATE as f32;
+    assert!(
+        (crossings as f32 - expected_crossings).abs() < expected_crossings * 0.1,
+        "{crossings} zero crossings, expected about {expected_crossings}"
+    );
+}